@@ -0,0 +1,113 @@
+//!
+//! # Write to multiple collections in batches
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{helper::BNC, Mapx, Vecx, DB_NUM};
+use rocksdb::WriteBatch;
+use ruc::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::Hash,
+    mem,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Collect the write operations of multiple collections,
+/// and commit them together.
+///
+/// **NOTE**: collections are hashed into different db instances by their paths,
+/// the operations are atomic within each db instance, but NOT across them.
+///
+/// **NOTE**: the lengths of collections are updated after the operations
+/// have been written by `commit()`, the pending operations will be discarded
+/// if the builder is dropped without committing.
+///
+/// **NOTE**: the writes are not recorded by the change log,
+/// the subscribers or the observers of a `Mapx`.
+pub struct WriteBatchBuilder {
+    batches: Vec<WriteBatch>,
+    staged: HashSet<(usize, Vec<u8>)>,
+    // (db index, prefix) => (the counter of the instance, the pending increment)
+    pending: HashMap<(usize, Vec<u8>), (Arc<AtomicUsize>, usize)>,
+}
+
+impl WriteBatchBuilder {
+    /// Create an empty builder.
+    #[inline(always)]
+    pub fn new() -> Self {
+        WriteBatchBuilder {
+            batches: (0..DB_NUM).map(|_| WriteBatch::default()).collect(),
+            staged: HashSet::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Add an insert operation of a Mapx.
+    #[inline(always)]
+    pub fn mapx_insert<K, V>(&mut self, map: &mut Mapx<K, V>, key: K, value: V)
+    where
+        K: Clone
+            + PartialEq
+            + Eq
+            + PartialOrd
+            + Ord
+            + Hash
+            + Serialize
+            + DeserializeOwned
+            + fmt::Debug,
+        V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+    {
+        let is_new =
+            pnk!(map.batch_set_value(&mut self.batches, &mut self.staged, key, value));
+        if is_new {
+            let (id, cnter) = map.counter();
+            self.pending.entry(id).or_insert((cnter, 0)).1 += 1;
+        }
+    }
+
+    /// Add a push operation of a Vecx.
+    #[inline(always)]
+    pub fn vecx_push<T>(&mut self, vec: &mut Vecx<T>, value: T)
+    where
+        T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+    {
+        let (id, cnter) = vec.counter();
+        let pending = self.pending.entry(id).or_insert((cnter, 0));
+        pnk!(vec.batch_push(&mut self.batches, pending.1, value));
+        pending.1 += 1;
+    }
+
+    /// Write all the pending operations to disk,
+    /// the lengths of collections are updated after each db instance
+    /// has been written successfully.
+    pub fn commit(mut self) -> Result<()> {
+        let pending = mem::take(&mut self.pending);
+        mem::take(&mut self.batches)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, b)| !b.is_empty())
+            .try_for_each(|(i, b)| {
+                BNC[i].write(b).c(d!())?;
+                pending.iter().filter(|((idx, _), _)| i == *idx).for_each(
+                    |(_, (cnter, n))| {
+                        cnter.fetch_add(*n, Ordering::Relaxed);
+                    },
+                );
+                Ok(())
+            })
+    }
+}
+
+impl Default for WriteBatchBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,55 @@
+//!
+//! # Test Cases
+//!
+
+use super::*;
+
+#[test]
+fn t_write_batch_builder() {
+    crate::clear();
+
+    let mut map = crate::new_mapx!();
+    let mut vec = crate::new_vecx!();
+
+    let mut batch = WriteBatchBuilder::new();
+    (0..100usize).for_each(|i| {
+        batch.mapx_insert(&mut map, i, i * 10);
+        batch.vecx_push(&mut vec, i);
+    });
+    batch.mapx_insert(&mut map, 0, 1);
+
+    assert!(map.get(&0).is_none());
+    assert!(vec.get(0).is_none());
+    assert_eq!(0, map.len());
+    assert_eq!(0, vec.len());
+
+    pnk!(batch.commit());
+
+    assert_eq!(100, map.len());
+    assert_eq!(100, vec.len());
+    assert_eq!(Some(1), map.get(&0));
+    assert_eq!(Some(990), map.get(&99));
+    assert_eq!(Some(99), vec.get(99));
+    assert_eq!(100, map.iter().count());
+    assert_eq!(100, vec.iter().count());
+
+    // discarded without committing
+    {
+        let mut batch = WriteBatchBuilder::default();
+        batch.mapx_insert(&mut map, 100, 1000);
+        batch.vecx_push(&mut vec, 100);
+    }
+    assert!(map.get(&100).is_none());
+    assert!(vec.get(100).is_none());
+    assert_eq!(100, map.len());
+    assert_eq!(100, vec.len());
+
+    let mut batch = WriteBatchBuilder::default();
+    batch.mapx_insert(&mut map, 100, 1000);
+    batch.vecx_push(&mut vec, 100);
+    batch.vecx_push(&mut vec, 101);
+    pnk!(batch.commit());
+    assert_eq!(101, map.len());
+    assert_eq!(102, vec.len());
+    assert_eq!(Some(101), vec.get(101));
+}
@@ -4,18 +4,32 @@
 
 use crate::{BNC_DATA_DIR, BNC_DATA_LIST, BNC_META_NAME, DB_NUM};
 use lazy_static::lazy_static;
-use rocksdb::{DBCompressionType, Options, SliceTransform, DB};
+#[cfg(feature = "merge-operator")]
+use rocksdb::MergeOperands;
+use rocksdb::{
+    DBCompressionType, DBIterator, Direction, IteratorMode, Options, ReadOptions,
+    SliceTransform, DB,
+};
 use ruc::*;
 use std::{
     collections::hash_map::DefaultHasher,
     convert::TryInto,
     fs,
     hash::{Hash, Hasher},
-    io::{Read, Write},
+    io::{ErrorKind, Read, Write},
     mem::size_of,
+    path::Path,
 };
+#[cfg(feature = "merge-operator")]
+use std::{collections::HashMap, sync::RwLock};
 
 pub(crate) const PREFIX: &str = "____prefix____";
+// The prefix shared by all the namespaces on a path.
+pub(crate) const NS_PREFIX: &str = "____ns_prefix____";
+pub(crate) const SCHEMA_VERSION: &str = "____schema_version____";
+pub(crate) const SCHEMA_TYPES: &str = "____schema_types____";
+#[cfg(feature = "merge-operator")]
+pub(crate) const MERGE_OPERATOR: &str = "____merge_operator____";
 const IDX_KEY: [u8; size_of::<u32>()] = u32::MAX.to_le_bytes();
 
 lazy_static! {
@@ -23,6 +37,62 @@ lazy_static! {
         (0..DB_NUM).map(|i| pnk!(rocksdb_open(i))).collect();
 }
 
+/// A full merge operator of an instance,
+/// the key passed to it does not contain the prefix of the instance.
+#[cfg(feature = "merge-operator")]
+pub(crate) type MergeOp = Box<
+    dyn Fn(&[u8], Option<&[u8]>, &mut MergeOperands) -> Option<Vec<u8>> + Send + Sync,
+>;
+
+// (db index, prefix of an instance) => (name, merge operator),
+// the prefixes are allocated by each db instance, so they may be the same.
+#[cfg(feature = "merge-operator")]
+type MergeOps = HashMap<(usize, Vec<u8>), (String, MergeOp)>;
+
+#[cfg(feature = "merge-operator")]
+lazy_static! {
+    pub(crate) static ref MERGE_OPS: RwLock<MergeOps> = RwLock::new(HashMap::new());
+}
+
+// The merge operator of a db instance is shared by all the collections in it,
+// so the real work is dispatched to the operator registered for each prefix.
+//
+// If no operator is found(e.g. the instance is opened without it after a restart),
+// the existing value is kept unchanged, or the latest operand is used if there is
+// no existing value, a `None` would make rocksdb treat the merge as a failure.
+#[cfg(feature = "merge-operator")]
+fn merge_dispatch(
+    idx: usize,
+    key: &[u8],
+    existing_val: Option<&[u8]>,
+    operands: &mut MergeOperands,
+) -> Option<Vec<u8>> {
+    let prefix_len = size_of::<u32>();
+    if let Ok(ops) = MERGE_OPS.read() {
+        let op = key
+            .get(..prefix_len)
+            .and_then(|p| ops.get(&(idx, p.to_vec())));
+        if let Some((_, op)) = op {
+            return op(&key[prefix_len..], existing_val, operands);
+        }
+    }
+    match existing_val {
+        Some(v) => Some(v.to_vec()),
+        None => operands.into_iter().last().map(|v| v.to_vec()),
+    }
+}
+
+// The partial merge is not supported,
+// the operands are always passed to the full merge operator.
+#[cfg(feature = "merge-operator")]
+fn merge_partial(
+    _key: &[u8],
+    _existing_val: Option<&[u8]>,
+    _operands: &mut MergeOperands,
+) -> Option<Vec<u8>> {
+    None
+}
+
 #[inline(always)]
 fn rocksdb_open(idx: usize) -> Result<DB> {
     let mut cfg = Options::default();
@@ -35,6 +105,14 @@ fn rocksdb_open(idx: usize) -> Result<DB> {
     cfg.create_missing_column_families(true);
     cfg.set_atomic_flush(true);
     cfg.set_prefix_extractor(SliceTransform::create_fixed_prefix(size_of::<u32>()));
+    #[cfg(feature = "merge-operator")]
+    cfg.set_merge_operator(
+        "bnc_merge_dispatch",
+        move |key: &[u8], existing_val: Option<&[u8]>, operands: &mut MergeOperands| {
+            merge_dispatch(idx, key, existing_val, operands)
+        },
+        merge_partial,
+    );
 
     let db = DB::open(&cfg, crate::BNC_DATA_LIST[idx].as_str()).c(d!())?;
 
@@ -61,10 +139,19 @@ pub(crate) fn rocksdb_clear() {
             BNC_META_NAME
         )));
     }
+
+    // The prefixes will be reused by new instances.
+    #[cfg(feature = "merge-operator")]
+    pnk!(MERGE_OPS.write().map_err(|e| eg!(e))).clear();
 }
 
 #[inline(always)]
 pub(crate) fn meta_check(path: &str) -> Result<()> {
+    meta_check_prefix(path, PREFIX).c(d!())
+}
+
+// Same as `meta_check`, but the new prefix is saved to the meta file `name`.
+pub(crate) fn meta_check_prefix(path: &str, name: &str) -> Result<()> {
     let idx = hash(&path) % DB_NUM;
     let path = format!("{}/{}", BNC_DATA_DIR.as_str(), path);
     fs::create_dir_all(&path).c(d!(path))?;
@@ -73,7 +160,7 @@ pub(crate) fn meta_check(path: &str) -> Result<()> {
         .create(true)
         .write(true)
         .read(true)
-        .open(format!("{}/{}", &path, PREFIX))
+        .open(format!("{}/{}", &path, name))
         .c(d!(path))?;
     let mut buf = [0u8; size_of::<u32>()];
     let nbytes = f.read(&mut buf).c(d!())?;
@@ -94,12 +181,117 @@ pub(crate) fn meta_check(path: &str) -> Result<()> {
     Ok(())
 }
 
+// Move the meta directory of an instance to a new path.
+pub(crate) fn meta_rename(old_path: &str, new_path: &str) -> Result<()> {
+    let old_path = format!("{}/{}", BNC_DATA_DIR.as_str(), old_path);
+    let new_path = format!("{}/{}", BNC_DATA_DIR.as_str(), new_path);
+
+    if fs::metadata(&new_path).is_ok() {
+        return Err(eg!(format!("'{}' already exists", new_path)));
+    }
+
+    if let Some(parent) = Path::new(&new_path).parent() {
+        fs::create_dir_all(parent).c(d!())?;
+    }
+
+    fs::rename(&old_path, &new_path).c(d!(new_path))
+}
+
+// Copy the extra meta files(e.g. the schema version) of an instance to a new path,
+// the prefix files are not copied, they belong to the db instance of the old path.
+pub(crate) fn meta_copy_extra(old_path: &str, new_path: &str) -> Result<()> {
+    let old_path = format!("{}/{}", BNC_DATA_DIR.as_str(), old_path);
+    let new_path = format!("{}/{}", BNC_DATA_DIR.as_str(), new_path);
+
+    for entry in fs::read_dir(&old_path).c(d!(old_path))? {
+        let entry = entry.c(d!())?;
+        let name = entry.file_name();
+        if entry.file_type().c(d!())?.is_file() && PREFIX != name && NS_PREFIX != name {
+            let dest = Path::new(&new_path).join(entry.file_name());
+            fs::copy(entry.path(), &dest).c(d!())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[inline(always)]
+pub(crate) fn meta_exists(path: &str) -> bool {
+    fs::metadata(format!("{}/{}", BNC_DATA_DIR.as_str(), path)).is_ok()
+}
+
+#[inline(always)]
+pub(crate) fn meta_remove(path: &str) -> Result<()> {
+    let path = format!("{}/{}", BNC_DATA_DIR.as_str(), path);
+    fs::remove_dir_all(&path).c(d!(path))
+}
+
 #[inline(always)]
 pub(crate) fn read_prefix_bytes(path: &str) -> Result<Vec<u8>> {
     let path = format!("{}/{}", BNC_DATA_DIR.as_str(), path);
     fs::read(&path).c(d!(path))
 }
 
+// Read an extra meta file of an instance, `None` means it has not been written.
+pub(crate) fn meta_read(path: &str, name: &str) -> Result<Option<Vec<u8>>> {
+    let path = format!("{}/{}/{}", BNC_DATA_DIR.as_str(), path, name);
+    match fs::read(&path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if ErrorKind::NotFound == e.kind() => Ok(None),
+        Err(e) => Err(eg!(e)).c(d!(path)),
+    }
+}
+
+#[inline(always)]
+pub(crate) fn meta_write(path: &str, name: &str, bytes: &[u8]) -> Result<()> {
+    let path = format!("{}/{}/{}", BNC_DATA_DIR.as_str(), path, name);
+    fs::write(&path, bytes).c(d!(path))
+}
+
+// The smallest key which is larger than all the keys with the prefix,
+// `None` means there is no such key(all bytes of the prefix are `u8::MAX`).
+pub(crate) fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut k = prefix.to_vec();
+    while let Some(last) = k.pop() {
+        if u8::MAX != last {
+            k.push(last + 1);
+            return Some(k);
+        }
+    }
+    None
+}
+
+// Iterate over the keys with the prefix,
+// unlike `DB::prefix_iterator`, the prefix can be longer than the prefix extractor.
+pub(crate) fn prefix_iter(idx: usize, prefix: &[u8]) -> DBIterator<'static> {
+    BNC[idx].iterator_opt(
+        IteratorMode::From(prefix, Direction::Forward),
+        prefix_read_opts(prefix),
+    )
+}
+
+// The read options limiting an iterator to the keys with `prefix`.
+#[inline(always)]
+pub(crate) fn prefix_read_opts(prefix: &[u8]) -> ReadOptions {
+    let mut opts = ReadOptions::default();
+    opts.set_prefix_same_as_start(true);
+    if let Some(upper) = prefix_upper_bound(prefix) {
+        opts.set_iterate_upper_bound(upper);
+    }
+    opts
+}
+
+// Get an integer property of the db instance,
+// `0` will be returned if the property is unavailable.
+#[inline(always)]
+pub(crate) fn rocksdb_property(idx: usize, name: &str) -> u64 {
+    BNC[idx]
+        .property_int_value(name)
+        .ok()
+        .flatten()
+        .unwrap_or(0)
+}
+
 #[inline(always)]
 pub(crate) fn hash<T: Hash>(t: &T) -> usize {
     let mut s = DefaultHasher::new();
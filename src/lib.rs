@@ -7,6 +7,8 @@
 
 ///////////////////////////////////////
 
+#[cfg(feature = "diskcache")]
+pub mod batch;
 #[cfg(feature = "diskcache")]
 mod helper;
 #[cfg(feature = "diskcache")]
@@ -18,12 +20,20 @@ mod serde;
 #[cfg(feature = "diskcache")]
 pub mod vecx;
 
+#[cfg(feature = "diskcache")]
+pub use batch::WriteBatchBuilder;
 #[cfg(feature = "diskcache")]
 pub use mapx::Mapx;
 #[cfg(feature = "diskcache")]
+pub use mapx::MapxBuilder;
+#[cfg(feature = "diskcache")]
+pub use mapx::MapxSet;
+#[cfg(feature = "diskcache")]
 pub use mapxnk::Mapxnk;
 #[cfg(feature = "diskcache")]
 pub use vecx::Vecx;
+#[cfg(feature = "diskcache")]
+pub use vecx::VecxFixed;
 
 ///////////////////////////////////////
 
@@ -155,6 +165,17 @@ macro_rules! try_twice {
     };
 }
 
+/// Same as `try_twice!`, but return the error of the second try instead of panicking.
+#[macro_export]
+macro_rules! try_twice_result {
+    ($ops: expr) => {
+        $ops.c(d!()).or_else(|e| {
+            e.print(None);
+            $ops.c(d!())
+        })
+    };
+}
+
 /// Generate a unique path for each instance.
 #[macro_export]
 macro_rules! unique_path {
@@ -171,6 +192,24 @@ macro_rules! unique_path {
     };
 }
 
+/// Generate a unique path under the given namespace,
+/// all the paths of a namespace can be cleaned up together.
+#[macro_export]
+macro_rules! unique_path_in {
+    ($ns: expr) => {
+        format!(
+            "{}/{}/{}_{}_{}_{}_{}",
+            $crate::BNC_META_NAME,
+            &*$ns,
+            ts!(),
+            file!(),
+            line!(),
+            column!(),
+            rand::random::<u32>()
+        )
+    };
+}
+
 /// A helper for creating Vecx.
 #[macro_export]
 macro_rules! new_vecx {
@@ -267,3 +306,37 @@ macro_rules! new_mapxnk_custom {
         $crate::try_twice!($crate::Mapxnk::new(&$crate::unique_path!()))
     }};
 }
+
+/// A helper for creating MapxSet.
+#[macro_export]
+macro_rules! new_mapx_set {
+    (@$ty: ty) => {
+        $crate::new_mapx_set_custom!(@$ty)
+    };
+    ($path:expr) => {
+        $crate::new_mapx_set_custom!($path)
+    };
+    () => {
+        $crate::new_mapx_set_custom!()
+    };
+}
+
+/// A helper for creating MapxSet.
+#[macro_export]
+macro_rules! new_mapx_set_custom {
+    (@$ty: ty) => {{
+        let obj: $crate::MapxSet<$ty> =
+            $crate::try_twice!($crate::MapxSet::new(&$crate::unique_path!()));
+        obj
+    }};
+    ($path: expr) => {{
+        $crate::try_twice!($crate::MapxSet::new(&format!(
+            "{}/{}",
+            $crate::BNC_META_NAME,
+            &*$path
+        )))
+    }};
+    () => {{
+        $crate::try_twice!($crate::MapxSet::new(&$crate::unique_path!()))
+    }};
+}
@@ -7,9 +7,10 @@
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
+    cmp::Reverse,
     collections::{
         btree_map::{Entry, IntoIter},
-        BTreeMap,
+        BTreeMap, BinaryHeap,
     },
     fmt,
     ops::RangeBounds,
@@ -135,4 +136,121 @@ where
     pub fn unset_value(&mut self, key: &K) {
         self.inner.remove(key);
     }
+
+    /// The `k` entries with the largest `key_fn(value)`, in descending order,
+    /// ties are broken by the order of keys.
+    pub fn top_k<F, O>(&self, k: usize, key_fn: F) -> Vec<(K, V)>
+    where
+        F: Fn(&V) -> O,
+        O: Ord,
+    {
+        // a min-heap of the largest ones so far
+        let mut heap = BinaryHeap::with_capacity(k.saturating_add(1));
+        for (key, v) in self.inner.iter() {
+            heap.push(Reverse((key_fn(v), key)));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((_, key))| (key.clone(), self.inner[key].clone()))
+            .collect()
+    }
+
+    /// The `k` entries with the smallest `key_fn(value)`, in ascending order,
+    /// ties are broken by the order of keys.
+    pub fn bottom_k<F, O>(&self, k: usize, key_fn: F) -> Vec<(K, V)>
+    where
+        F: Fn(&V) -> O,
+        O: Ord,
+    {
+        // a max-heap of the smallest ones so far
+        let mut heap = BinaryHeap::with_capacity(k.saturating_add(1));
+        for (key, v) in self.inner.iter() {
+            heap.push((key_fn(v), key));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(_, key)| (key.clone(), self.inner[key].clone()))
+            .collect()
+    }
+
+    /// Insert all the entries of `other`,
+    /// `resolve(key, self_value, other_value)` decides the value
+    /// if a key exists in both instances, same as `Mapx::union_with`.
+    pub fn union_with<F>(&mut self, other: &Mapi<K, V>, mut resolve: F)
+    where
+        F: FnMut(&K, &V, &V) -> V,
+    {
+        for (k, v) in other.inner.iter() {
+            match self.inner.entry(k.clone()) {
+                Entry::Occupied(mut e) => {
+                    let new_v = resolve(k, e.get(), v);
+                    e.insert(new_v);
+                }
+                Entry::Vacant(e) => {
+                    e.insert(v.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Player {
+        score: usize,
+    }
+
+    #[test]
+    fn t_top_bottom_k() {
+        let mut db = pnk!(Mapi::new(""));
+        (0..100usize).for_each(|i| {
+            db.set_value(
+                i,
+                Player {
+                    score: (i * 37) % 100,
+                },
+            )
+        });
+
+        let top = db.top_k(5, |v| v.score);
+        assert_eq!(
+            vec![99, 98, 97, 96, 95],
+            top.iter().map(|(_, v)| v.score).collect::<Vec<_>>()
+        );
+        top.iter()
+            .for_each(|(k, v)| assert_eq!(Some(v.clone()), db.get(k)));
+
+        let bottom = db.bottom_k(5, |v| v.score);
+        assert_eq!(
+            vec![0, 1, 2, 3, 4],
+            bottom.iter().map(|(_, v)| v.score).collect::<Vec<_>>()
+        );
+
+        assert!(db.top_k(0, |v| v.score).is_empty());
+        assert_eq!(100, db.bottom_k(1000, |v| v.score).len());
+    }
+
+    #[test]
+    fn t_union_with() {
+        let mut a = pnk!(Mapi::new(""));
+        let mut b = pnk!(Mapi::new(""));
+        (0..10usize).for_each(|i| a.set_value(i, i));
+        (5..15usize).for_each(|i| b.set_value(i, 20 - i));
+
+        a.union_with(&b, |_, x, y| *x.max(y));
+        assert_eq!(15, a.len());
+        (0..5).for_each(|i| assert_eq!(Some(i), a.get(&i)));
+        (5..10).for_each(|i| assert_eq!(Some(i.max(20 - i)), a.get(&i)));
+        (10..15).for_each(|i| assert_eq!(Some(20 - i), a.get(&i)));
+        assert_eq!(10, b.len());
+    }
 }
@@ -2,11 +2,87 @@
 //! # Disk Storage Implementation
 //!
 
-use crate::{helper::*, DB_NUM};
-use rocksdb::{DBIterator, DBPinnableSlice};
+#[cfg(feature = "observe")]
+use super::MapxChangeEvent;
+#[cfg(feature = "async")]
+use super::MapxEvent;
+use super::{
+    ChangeLogEntry, ChangeOp, MapxMetrics, MemoryBreakdown, PatchEntry, ValidationReport,
+};
+use crate::{helper::*, BNC_DATA_LIST, DB_NUM};
+use lazy_static::lazy_static;
+use rocksdb::{
+    DBIterator, DBPinnableSlice, Direction, IteratorMode, ReadOptions, WriteBatch,
+};
 use ruc::*;
 use serde::{de::DeserializeOwned, Serialize};
-use std::{fmt, hash::Hash, iter::Iterator, marker::PhantomData};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    fs::{self, File, OpenOptions},
+    hash::Hash,
+    io::{BufWriter, Write},
+    iter::Iterator,
+    marker::PhantomData,
+    mem::size_of,
+    ops::RangeBounds,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+#[cfg(feature = "async")]
+use tokio::sync::broadcast;
+
+#[cfg(feature = "async")]
+const EVENT_CHANNEL_SIZE: usize = 1024;
+
+// The event channel of an instance, shared by all the clones of it,
+// it is created on the first subscription.
+#[cfg(feature = "async")]
+type Events<K, V> = Mutex<Option<broadcast::Sender<MapxEvent<K, V>>>>;
+
+// A callback registered by `Mapx::register_observer`.
+#[cfg(feature = "observe")]
+type Observer<K, V> = Arc<dyn Fn(MapxChangeEvent<K, V>) + Send + Sync>;
+
+// The observers of an instance, shared by all the clones of it,
+// the list is replaced on registration, so it can be called without the lock.
+#[cfg(feature = "observe")]
+struct Observers<K, V>(Mutex<Arc<Vec<Observer<K, V>>>>);
+
+#[cfg(feature = "observe")]
+impl<K, V> fmt::Debug for Observers<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = self.0.lock().map(|o| o.len()).unwrap_or(0);
+        write!(f, "Observers({})", n)
+    }
+}
+
+// The bounds of the memtable size set by `capacity_hint`,
+// the lower one is the default value of rocksdb;
+// at most `MAX_WRITE_BUFFER_NUM` memtables are used,
+// so the budget of a db instance is capped at 1GB.
+const MIN_WRITE_BUFFER_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_WRITE_BUFFER_SIZE: u64 = 256 * 1024 * 1024;
+
+// The number of memtables, the lower one is the default value of rocksdb.
+const MIN_WRITE_BUFFER_NUM: u32 = 2;
+const MAX_WRITE_BUFFER_NUM: u32 = 4;
+
+// Used by `capacity_hint` if there is no entry to measure.
+const DEFAULT_ENTRY_SIZE: u64 = 256;
+
+// The number of the locks used by `atomic_rmw`.
+const RMW_LOCK_NUM: usize = 64;
+
+lazy_static! {
+    // Make the check-and-write steps of `atomic_rmw` atomic, striped by the raw key.
+    static ref RMW_LOCKS: Vec<Mutex<()>> =
+        (0..RMW_LOCK_NUM).map(|_| Mutex::new(())).collect();
+}
 
 // To solve the problem of unlimited memory usage,
 // use this to replace the original in-memory `HashMap<_, _>`.
@@ -17,9 +93,16 @@ where
     V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
 {
     path: String,
-    cnter: usize,
+    cnter: Arc<AtomicUsize>,
     prefix: Vec<u8>,
     idx: usize,
+    #[cfg(feature = "async")]
+    events: Arc<Events<K, V>>,
+    #[cfg(feature = "observe")]
+    observers: Arc<Observers<K, V>>,
+    change_log: Option<Arc<Mutex<BufWriter<File>>>>,
+    metrics: Option<Arc<MapxMetrics>>,
+    eviction: Option<Arc<Mutex<AccessTracker<K, V>>>>,
     _pd0: PhantomData<K>,
     _pd1: PhantomData<V>,
 }
@@ -38,58 +121,646 @@ where
     // Or it will create a new one.
     #[inline(always)]
     pub(super) fn load_or_create(path: &str) -> Result<Self> {
+        Self::load_or_create_in_namespace(path, &[]).c(d!())
+    }
+
+    // Same as `load_or_create`, but the keys are stored under the namespace prefix
+    // of the path, followed by the length-prefixed namespace,
+    // so the instances on the same path are isolated from each other;
+    // an empty namespace means the plain instance of the path.
+    pub(super) fn load_or_create_in_namespace(
+        path: &str,
+        namespace: &[u8],
+    ) -> Result<Self> {
         meta_check(path).c(d!())?;
-        let prefix = read_prefix_bytes(&format!("{}/{}", path, PREFIX)).c(d!())?;
+        let prefix = if namespace.is_empty() {
+            read_prefix_bytes(&format!("{}/{}", path, PREFIX)).c(d!())?
+        } else {
+            meta_check_prefix(path, NS_PREFIX).c(d!())?;
+            let mut prefix =
+                read_prefix_bytes(&format!("{}/{}", path, NS_PREFIX)).c(d!())?;
+            prefix.append(&mut bincode::serialize(namespace).c(d!())?);
+            prefix
+        };
         let idx = hash(&path) % DB_NUM;
 
         Ok(Mapx {
             path: path.to_owned(),
-            cnter: BNC[idx].prefix_iterator(&prefix).count(),
+            cnter: Arc::new(AtomicUsize::new(prefix_iter(idx, &prefix).count())),
             prefix,
             idx,
+            #[cfg(feature = "async")]
+            events: Arc::new(Mutex::new(None)),
+            #[cfg(feature = "observe")]
+            observers: Arc::new(Observers(Mutex::new(Arc::new(vec![])))),
+            change_log: None,
+            metrics: None,
+            eviction: None,
             _pd0: PhantomData,
             _pd1: PhantomData,
         })
     }
 
+    // Read an extra meta file of the instance.
+    #[inline(always)]
+    pub(super) fn read_meta(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        meta_read(&self.path, &self.meta_name(name)).c(d!())
+    }
+
+    #[inline(always)]
+    pub(super) fn write_meta(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        meta_write(&self.path, &self.meta_name(name), bytes).c(d!())
+    }
+
+    // The meta of a path is shared by all the namespaces on it,
+    // so the extra meta files of a namespace are suffixed with its hex.
+    fn meta_name(&self, name: &str) -> String {
+        let ns = self.namespace();
+        if ns.is_empty() {
+            name.to_owned()
+        } else {
+            ns.iter()
+                .fold(format!("{}.", name), |acc, b| format!("{}{:02x}", acc, b))
+        }
+    }
+
+    // The namespace appended to the prefix(after its length), empty if there is none.
+    #[inline(always)]
+    pub(super) fn namespace(&self) -> &[u8] {
+        self.prefix
+            .get(size_of::<u32>() + size_of::<u64>()..)
+            .unwrap_or_default()
+    }
+
+    // Append all the following writes to a NDJSON file.
+    pub(super) fn set_change_log(&mut self, log_path: &str) -> Result<()> {
+        let f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path)
+            .c(d!(log_path))?;
+        self.change_log = Some(Arc::new(Mutex::new(BufWriter::new(f))));
+        Ok(())
+    }
+
+    // Start counting the operations of the instance.
+    #[inline(always)]
+    pub(super) fn enable_metrics(&mut self) {
+        self.metrics = Some(Arc::new(MapxMetrics::default()));
+    }
+
+    #[inline(always)]
+    pub(super) fn metrics(&self) -> Option<&MapxMetrics> {
+        self.metrics.as_deref()
+    }
+
+    // Move the keys which are not accessed in the last `access_threshold` writes
+    // to `cold_store`, all the existing keys are regarded as just accessed.
+    pub(super) fn set_eviction(
+        &mut self,
+        cold_store: Mapx<K, V>,
+        access_threshold: usize,
+    ) {
+        let mut tracker = AccessTracker {
+            cold_store,
+            access_threshold: access_threshold as u64,
+            op: 0,
+            last_access: HashMap::new(),
+            by_op: BTreeSet::new(),
+        };
+        prefix_iter(self.idx, &self.prefix).for_each(|(k, _)| tracker.touch(&k));
+        self.eviction = Some(Arc::new(Mutex::new(tracker)));
+    }
+
+    // Read the keys in a background thread to fill the block cache.
+    pub(super) fn warm_up(&self, keys: &[K]) {
+        let idx = self.idx;
+        let keys = keys
+            .iter()
+            .map(|key| {
+                let mut k = self.prefix.clone();
+                k.append(&mut pnk!(bincode::serialize(key)));
+                k
+            })
+            .collect::<Vec<_>>();
+
+        thread::spawn(move || warm_up_raw(idx, keys));
+    }
+
+    // The storage order of the keys may differ from their `Ord`,
+    // so the keys in the range are found by a scan without filling the cache,
+    // and then read like `warm_up`.
+    pub(super) fn warm_up_range<R>(&self, range: R)
+    where
+        K: PartialOrd + Send + 'static,
+        R: RangeBounds<K> + Send + 'static,
+    {
+        let idx = self.idx;
+        let prefix = self.prefix.clone();
+
+        thread::spawn(move || {
+            let mut opts = ReadOptions::default();
+            opts.fill_cache(false);
+            let keys = BNC[idx]
+                .iterator_opt(IteratorMode::From(&prefix, Direction::Forward), opts)
+                .take_while(|(k, _)| k.starts_with(&prefix))
+                .filter(|(k, _)| {
+                    bincode::deserialize::<K>(&k[prefix.len()..])
+                        .map(|key| range.contains(&key))
+                        .unwrap_or(false)
+                })
+                .map(|(k, _)| k.to_vec())
+                .collect::<Vec<_>>();
+            warm_up_raw(idx, keys);
+        });
+    }
+
+    // Look up a key in the cold store, if any.
+    fn get_cold(&self, key: &K) -> Option<V> {
+        self.eviction
+            .as_ref()
+            .and_then(|t| pnk!(t.lock().map_err(|e| eg!(e))).cold_store.get(key))
+    }
+
+    // Record an access to a key of the primary store.
+    #[inline(always)]
+    fn touch(&self, k: &[u8]) {
+        if let Some(t) = self.eviction.as_ref() {
+            pnk!(t.lock().map_err(|e| eg!(e))).touch(k);
+        }
+    }
+
+    // Count a write to `k`, and move the expired keys to the cold store.
+    #[inline(always)]
+    fn track_write(&mut self, key: &K, k: &[u8]) {
+        self.track_writes([(key, k)]);
+    }
+
+    // Count the writes of one batch, and move the expired keys to the cold store,
+    // the eviction is checked after all the keys have been counted,
+    // so no key of the batch is evicted before its own write is counted.
+    fn track_writes<'a, I>(&mut self, written: I)
+    where
+        I: IntoIterator<Item = (&'a K, &'a [u8])>,
+        K: 'a,
+    {
+        let t = match self.eviction.clone() {
+            Some(t) => t,
+            None => return,
+        };
+        let mut t = pnk!(t.lock().map_err(|e| eg!(e)));
+
+        for (key, k) in written {
+            // the primary store holds the latest value now
+            t.cold_store.unset_value(key);
+            t.op += 1;
+            t.touch(k);
+        }
+
+        for ek in t.pop_expired() {
+            if let Some(v) = pnk!(BNC[self.idx].get(&ek)) {
+                // put it into the cold store before deleting, so it is never lost
+                t.cold_store.set_value(
+                    pnk!(bincode::deserialize(&ek[self.prefix.len()..])),
+                    pnk!(serde_json::from_slice(&v)),
+                );
+                pnk!(BNC[self.idx].delete(ek));
+                self.cnter.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn is_tracked(&self) -> bool {
+        self.eviction.is_some()
+    }
+
+    // Stop tracking a removed key, return if it has been removed from the cold store.
+    fn track_remove(&mut self, key: &K, k: &[u8]) -> bool {
+        if let Some(t) = self.eviction.as_ref() {
+            let mut t = pnk!(t.lock().map_err(|e| eg!(e)));
+            t.untrack(k);
+            t.cold_store.unset_value(key).is_some()
+        } else {
+            false
+        }
+    }
+
+    // Flush the db instance and the change log.
+    pub(super) fn flush(&self) -> Result<()> {
+        BNC[self.idx].flush().c(d!())?;
+        if let Some(log) = self.change_log.as_ref() {
+            log.lock().map_err(|e| eg!(e))?.flush().c(d!())?;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn log_change(&self, op: ChangeOp, key: Option<&K>, value: Option<&V>) {
+        if let Some(log) = self.change_log.as_ref() {
+            let entry = ChangeLogEntry {
+                op,
+                key,
+                value,
+                ts: ts!(),
+            };
+            let mut log = pnk!(log.lock().map_err(|e| eg!(e)));
+            pnk!(serde_json::to_writer(&mut *log, &entry));
+            pnk!(log.write_all(b"\n"));
+        }
+    }
+
+    // Subscribe the write events of this instance,
+    // the channel is created on the first subscription.
+    #[cfg(feature = "async")]
+    pub(super) fn subscribe(&self) -> broadcast::Receiver<MapxEvent<K, V>> {
+        pnk!(self.events.lock().map_err(|e| eg!(e)))
+            .get_or_insert_with(|| broadcast::channel(EVENT_CHANNEL_SIZE).0)
+            .subscribe()
+    }
+
+    #[cfg(feature = "async")]
+    fn publish(&self, event: impl FnOnce() -> MapxEvent<K, V>) {
+        if let Some(tx) = pnk!(self.events.lock().map_err(|e| eg!(e)))
+            .as_ref()
+            .filter(|tx| 0 < tx.receiver_count())
+        {
+            // An error means all the receivers have been dropped.
+            omit!(tx.send(event()));
+        }
+    }
+
+    #[cfg(feature = "observe")]
+    #[inline(always)]
+    pub(super) fn register_observer(&mut self, observer: Observer<K, V>) {
+        let mut observers = pnk!(self.observers.0.lock().map_err(|e| eg!(e)));
+        Arc::make_mut(&mut observers).push(observer);
+    }
+
+    #[cfg(feature = "observe")]
+    #[inline(always)]
+    pub(super) fn remove_all_observers(&mut self) {
+        *pnk!(self.observers.0.lock().map_err(|e| eg!(e))) = Arc::new(vec![]);
+    }
+
+    #[cfg(feature = "observe")]
+    fn observers(&self) -> Arc<Vec<Observer<K, V>>> {
+        Arc::clone(&*pnk!(self.observers.0.lock().map_err(|e| eg!(e))))
+    }
+
+    #[cfg(feature = "observe")]
+    fn notify_observers(&self, event: impl FnOnce() -> MapxChangeEvent<K, V>) {
+        // do not hold the lock, an observer may write to this instance again
+        let observers = self.observers();
+        if !observers.is_empty() {
+            let event = event();
+            observers.iter().for_each(|o| o(event.clone()));
+        }
+    }
+
+    // Whether the writes are watched by the change log, any observer or subscriber,
+    // if not, the batch operations need not to keep the written entries.
+    fn is_watched(&self) -> bool {
+        if self.change_log.is_some() {
+            return true;
+        }
+        #[cfg(feature = "observe")]
+        if !self.observers().is_empty() {
+            return true;
+        }
+        #[cfg(feature = "async")]
+        if pnk!(self.events.lock().map_err(|e| eg!(e)))
+            .as_ref()
+            .map(|tx| 0 < tx.receiver_count())
+            .unwrap_or(false)
+        {
+            return true;
+        }
+        false
+    }
+
+    // Log an insertion, and publish it to the observers and the subscribers.
+    fn after_insert(&self, key: &K, value: &V) {
+        self.log_change(ChangeOp::Insert, Some(key), Some(value));
+        #[cfg(feature = "observe")]
+        self.notify_observers(|| MapxChangeEvent::KeyInserted {
+            key: key.clone(),
+            value: value.clone(),
+        });
+        #[cfg(feature = "async")]
+        self.publish(|| MapxEvent::Inserted(key.clone(), value.clone()));
+    }
+
+    // Log a removal, and publish it to the observers and the subscribers.
+    fn after_remove(&self, key: &K) {
+        self.log_change(ChangeOp::Remove, Some(key), None);
+        #[cfg(feature = "observe")]
+        self.notify_observers(|| MapxChangeEvent::KeyRemoved { key: key.clone() });
+        #[cfg(feature = "async")]
+        self.publish(|| MapxEvent::Removed(key.clone()));
+    }
+
+    // Log the removal of all the entries,
+    // and publish it to the observers and the subscribers.
+    fn after_clear(&self) {
+        self.log_change(ChangeOp::Clear, None, None);
+        #[cfg(feature = "observe")]
+        self.notify_observers(|| MapxChangeEvent::Cleared);
+        #[cfg(feature = "async")]
+        self.publish(|| MapxEvent::Cleared);
+    }
+
     // Get the storage path
     pub(super) fn get_path(&self) -> &str {
         self.path.as_str()
     }
 
+    // Move the instance to a new path,
+    // the data will be moved too if the new path is hashed to another db instance.
+    pub(super) fn rename(&mut self, new_path: &str) -> Result<()> {
+        // The meta of a path is shared by all the namespaces on it.
+        if !self.namespace().is_empty() {
+            return Err(eg!("a namespaced instance can not be renamed"));
+        }
+
+        let new_idx = hash(&new_path) % DB_NUM;
+        if new_idx == self.idx {
+            meta_rename(&self.path, new_path).c(d!())?;
+            self.path = new_path.to_owned();
+            return Ok(());
+        }
+
+        if meta_exists(new_path) {
+            return Err(eg!(format!("'{}' already exists", new_path)));
+        }
+
+        // The namespaces on the path are left in the old db instance.
+        if meta_read(&self.path, NS_PREFIX).c(d!())?.is_some() {
+            return Err(eg!("the namespaces on the path can not be moved"));
+        }
+
+        // The prefix is allocated by each db instance,
+        // so a new prefix is needed in the new db instance.
+        meta_check(new_path).c(d!())?;
+        let new_prefix =
+            read_prefix_bytes(&format!("{}/{}", new_path, PREFIX)).c(d!())?;
+
+        let mut new_batch = WriteBatch::default();
+        let mut old_batch = WriteBatch::default();
+        for (k, v) in prefix_iter(self.idx, &self.prefix) {
+            let mut new_k = new_prefix.clone();
+            new_k.extend_from_slice(&k[self.prefix.len()..]);
+            new_batch.put(new_k, v);
+            old_batch.delete(k);
+        }
+        BNC[new_idx].write(new_batch).c(d!())?;
+        BNC[self.idx].write(old_batch).c(d!())?;
+
+        // keep the schema version and the merge operator of the instance
+        meta_copy_extra(&self.path, new_path).c(d!())?;
+        #[cfg(feature = "merge-operator")]
+        {
+            let mut ops = MERGE_OPS.write().map_err(|e| eg!(e))?;
+            if let Some(op) = ops.remove(&(self.idx, self.prefix.clone())) {
+                ops.insert((new_idx, new_prefix.clone()), op);
+            }
+        }
+        meta_remove(&self.path).c(d!())?;
+
+        self.path = new_path.to_owned();
+        self.prefix = new_prefix;
+        self.idx = new_idx;
+
+        Ok(())
+    }
+
     // Imitate the behavior of 'HashMap<_>.get(...)'
     #[inline(always)]
     pub(super) fn get(&self, key: &K) -> Option<V> {
         let mut k = self.prefix.clone();
         k.append(&mut pnk!(bincode::serialize(key)));
-        BNC[self.idx]
-            .get(k)
-            .ok()
-            .flatten()
-            .map(|bytes| pnk!(serde_json::from_slice(&bytes)))
+        match BNC[self.idx].get(&k).ok().flatten() {
+            Some(bytes) => {
+                self.touch(&k);
+                Some(pnk!(serde_json::from_slice(&bytes)))
+            }
+            None => self.get_cold(key),
+        }
+    }
+
+    // Read the value of a serialized key without decoding,
+    // the cold store is not searched.
+    #[inline(always)]
+    pub(super) fn get_raw_bytes(&self, raw_key: &[u8]) -> Option<Vec<u8>> {
+        let mut k = self.prefix.clone();
+        k.extend_from_slice(raw_key);
+        pnk!(BNC[self.idx].get(&k))
+    }
+
+    // Write a serialized entry without checking it.
+    pub(super) fn insert_raw_bytes(&mut self, mut raw_key: Vec<u8>, raw_value: Vec<u8>) {
+        let mut k = self.prefix.clone();
+        k.append(&mut raw_key);
+        if pnk!(BNC[self.idx].get_pinned(&k)).is_none() {
+            self.cnter.fetch_add(1, Ordering::Relaxed);
+        }
+        pnk!(BNC[self.idx].put(k, raw_value));
+    }
+
+    // Read all the keys from one snapshot,
+    // so the results are consistent with each other.
+    pub(super) fn get_consistent(&self, keys: &[&K]) -> Vec<Option<V>> {
+        let snapshot = BNC[self.idx].snapshot();
+        keys.iter()
+            .map(|key| {
+                let mut k = self.prefix.clone();
+                k.append(&mut pnk!(bincode::serialize(key)));
+                pnk!(snapshot.get(k)).map(|bytes| pnk!(serde_json::from_slice(&bytes)))
+            })
+            .collect()
     }
 
     // Imitate the behavior of 'HashMap<_>.len()'.
     #[inline(always)]
     pub(super) fn len(&self) -> usize {
         debug_assert_eq!(
-            BNC[self.idx].prefix_iterator(&self.prefix).count(),
-            self.cnter
+            prefix_iter(self.idx, &self.prefix).count(),
+            self.cnter.load(Ordering::Relaxed)
+        );
+        self.cnter.load(Ordering::Relaxed)
+    }
+
+    // The total size of all the SST files of the db instance.
+    #[inline(always)]
+    pub(super) fn approximate_size_bytes(&self) -> u64 {
+        BNC[self.idx]
+            .live_files()
+            .map(|files| files.iter().map(|f| f.size as u64).sum())
+            .unwrap_or(0)
+    }
+
+    // The modification time of the newest SST file whose key range covers the key,
+    // the current time is used if there is no such file(not flushed yet).
+    pub(super) fn write_time_hint(&self, key: &K) -> SystemTime {
+        let mut k = self.prefix.clone();
+        k.append(&mut pnk!(bincode::serialize(key)));
+
+        BNC[self.idx]
+            .live_files()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|f| {
+                f.start_key.as_deref().map(|s| s <= &k[..]).unwrap_or(false)
+                    && f.end_key.as_deref().map(|e| &k[..] <= e).unwrap_or(false)
+            })
+            .filter_map(|f| {
+                fs::metadata(format!("{}{}", BNC_DATA_LIST[self.idx], f.name))
+                    .and_then(|m| m.modified())
+                    .ok()
+            })
+            .max()
+            .unwrap_or_else(SystemTime::now)
+    }
+
+    // The average size of the entries of the db instance,
+    // `None` if the properties are unavailable.
+    pub(super) fn average_entry_size_bytes(&self) -> Option<u64> {
+        let n = rocksdb_property(self.idx, "rocksdb.estimate-num-keys");
+        let size = rocksdb_property(self.idx, "rocksdb.estimate-live-data-size")
+            + rocksdb_property(self.idx, "rocksdb.cur-size-all-mem-tables");
+        alt!(0 == n || 0 == size, None, Some(size / n))
+    }
+
+    // Size the memtables of the db instance for `n` more entries,
+    // return the options set.
+    pub(super) fn capacity_hint(&mut self, n: usize) -> Result<(u64, u32)> {
+        let entry_size = self
+            .average_entry_size_bytes()
+            .unwrap_or(DEFAULT_ENTRY_SIZE);
+        let buf_size = (n as u64)
+            .saturating_mul(entry_size)
+            .clamp(MIN_WRITE_BUFFER_SIZE, MAX_WRITE_BUFFER_SIZE);
+        // more buffers for the large memtables,
+        // so the writes do not stall while one is being flushed
+        let buf_num = alt!(
+            MIN_WRITE_BUFFER_SIZE < buf_size,
+            MAX_WRITE_BUFFER_NUM,
+            MIN_WRITE_BUFFER_NUM
         );
-        self.cnter
+
+        set_write_buffer(self.idx, buf_size, buf_num)
+            .c(d!())
+            .map(|_| (buf_size, buf_num))
+    }
+
+    #[inline(always)]
+    pub(super) fn reset_capacity_hint(&mut self) -> Result<()> {
+        set_write_buffer(self.idx, MIN_WRITE_BUFFER_SIZE, MIN_WRITE_BUFFER_NUM).c(d!())
+    }
+
+    // The memory used by the block cache and the memtables of the db instance.
+    #[inline(always)]
+    pub(super) fn approximate_memory_bytes(&self) -> u64 {
+        rocksdb_property(self.idx, "rocksdb.block-cache-usage")
+            + rocksdb_property(self.idx, "rocksdb.cur-size-all-mem-tables")
+    }
+
+    // The memory usage of the db instance by components.
+    pub(super) fn memory_breakdown(&self) -> Result<MemoryBreakdown> {
+        let stats = rocksdb::perf::get_memory_usage_stats(Some(&[&BNC[self.idx]]), None)
+            .c(d!())?;
+        Ok(MemoryBreakdown {
+            block_cache_bytes: rocksdb_property(self.idx, "rocksdb.block-cache-usage"),
+            write_buffer_bytes: stats.mem_table_unflushed,
+            table_readers_bytes: stats.mem_table_readers_total,
+            misc_bytes: stats
+                .mem_table_total
+                .saturating_sub(stats.mem_table_unflushed),
+        })
+    }
+
+    // The fragmentation of the db instance,
+    // both the SST files and the memtables are counted.
+    pub(super) fn fragmentation_ratio(&self) -> f64 {
+        let (mut entries, mut deletions) = BNC[self.idx]
+            .live_files()
+            .map(|files| {
+                files.iter().fold((0, 0), |acc, f| {
+                    (acc.0 + f.num_entries, acc.1 + f.num_deletions)
+                })
+            })
+            .unwrap_or((0, 0));
+
+        for t in ["active-mem-table", "imm-mem-tables"] {
+            entries += rocksdb_property(self.idx, &format!("rocksdb.num-entries-{}", t));
+            deletions +=
+                rocksdb_property(self.idx, &format!("rocksdb.num-deletes-{}", t));
+        }
+
+        // `num_entries` of rocksdb includes the deletions,
+        // and every deletion kills one of the other entries
+        let puts = entries.saturating_sub(deletions);
+        fragmentation(puts.saturating_sub(deletions), deletions)
+    }
+
+    // Scan all the raw entries, and check if they can be decoded.
+    pub(super) fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport {
+            len: self.cnter.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+
+        for (k, v) in prefix_iter(self.idx, &self.prefix) {
+            report.entries += 1;
+            let key_ok = bincode::deserialize::<K>(&k[self.prefix.len()..]).is_ok();
+            let value_ok = serde_json::from_slice::<V>(&v).is_ok();
+            if !key_ok {
+                report.invalid_keys += 1;
+            }
+            if !value_ok {
+                report.invalid_values += 1;
+            }
+            if key_ok && value_ok {
+                report.valid_entries += 1;
+            }
+        }
+
+        report
+    }
+
+    // Compact the whole db instance,
+    // which is shared by all the collections hashed into it.
+    #[inline(always)]
+    pub(super) fn compact_all(&self) {
+        BNC[self.idx].compact_range(None::<&[u8]>, None::<&[u8]>);
+    }
+
+    // Similar with `compact_all`, but do it in a new thread,
+    // an error will be returned if it is not finished in time,
+    // and the compaction will continue in the background.
+    pub(super) fn compact_with_timeout(&self, dur: Duration) -> Result<()> {
+        let idx = self.idx;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            BNC[idx].compact_range(None::<&[u8]>, None::<&[u8]>);
+            omit!(tx.send(()));
+        });
+        rx.recv_timeout(dur).c(d!("compaction timeout"))
     }
 
     // A helper func
     #[inline(always)]
     pub(super) fn is_empty(&self) -> bool {
-        BNC[self.idx].prefix_iterator(&self.prefix).next().is_none()
+        prefix_iter(self.idx, &self.prefix).next().is_none()
     }
 
     // Imitate the behavior of 'HashMap<_>.insert(...)'.
     #[inline(always)]
     pub(super) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let cold_v = self.get_cold(&key);
         self.set_value(key, value)
             .map(|v| pnk!(serde_json::from_slice(&v)))
+            .or(cold_v)
     }
 
     // Similar with `insert`, but ignore if the old value is exist.
@@ -100,19 +771,289 @@ where
         let v = pnk!(serde_json::to_vec(&value));
         let old_v = pnk!(BNC[self.idx].get_pinned(&k));
 
-        pnk!(BNC[self.idx].put(k, v));
+        pnk!(BNC[self.idx].put(&k, v));
 
         if old_v.is_none() {
-            self.cnter += 1;
+            self.cnter.fetch_add(1, Ordering::Relaxed);
         }
 
+        self.track_write(&key, &k);
+
+        self.after_insert(&key, &value);
+
         old_v
     }
 
+    // Similar with `set_value`, but write all the entries in one batch.
+    pub(super) fn set_value_batch<I>(&mut self, entries: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut batch = WriteBatch::default();
+        let mut new_keys = HashSet::new();
+        let keep = self.is_watched() || self.is_tracked();
+        let mut written = vec![];
+
+        for (key, value) in entries {
+            let mut k = self.prefix.clone();
+            k.append(&mut bincode::serialize(&key).c(d!())?);
+            let v = serde_json::to_vec(&value).c(d!())?;
+            if !new_keys.contains(&k) && BNC[self.idx].get_pinned(&k).c(d!())?.is_none()
+            {
+                new_keys.insert(k.clone());
+            }
+            batch.put(&k, v);
+            alt!(keep, written.push((key, k, value)));
+        }
+
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.fetch_add(new_keys.len(), Ordering::Relaxed);
+
+        self.track_writes(written.iter().map(|(key, k, _)| (key, k.as_slice())));
+        written
+            .iter()
+            .for_each(|(key, _, v)| self.after_insert(key, v));
+
+        Ok(())
+    }
+
+    // Seek to a random position, and wrap around to the first entry
+    // if there is no entry after that position.
+    pub(super) fn random_key(&self) -> Option<K> {
+        let mut k = self.prefix.clone();
+        k.extend_from_slice(&rand::random::<[u8; 8]>());
+
+        let mut i = prefix_iter(self.idx, &self.prefix);
+        i.set_mode(IteratorMode::From(&k, Direction::Forward));
+
+        i.next()
+            .or_else(|| prefix_iter(self.idx, &self.prefix).next())
+            .map(|(k, _)| pnk!(bincode::deserialize(&k[self.prefix.len()..])))
+    }
+
+    // Similar with `set_value`, but the entry is added into a batch,
+    // and the counter is updated by the caller after the batch has been written;
+    // `staged` holds the new keys that have been added into the batches,
+    // return `true` if the key is a new one.
+    pub(super) fn batch_set_value(
+        &self,
+        batches: &mut [WriteBatch],
+        staged: &mut HashSet<(usize, Vec<u8>)>,
+        key: K,
+        value: V,
+    ) -> Result<bool> {
+        let mut k = self.prefix.clone();
+        k.append(&mut bincode::serialize(&key).c(d!())?);
+        let v = serde_json::to_vec(&value).c(d!())?;
+
+        let staged_key = (self.idx, k);
+        let is_new = !staged.contains(&staged_key)
+            && BNC[self.idx].get_pinned(&staged_key.1).c(d!())?.is_none();
+        batches[self.idx].put(&staged_key.1, v);
+        if is_new {
+            staged.insert(staged_key);
+        }
+
+        Ok(is_new)
+    }
+
+    // The db index and the prefix identify an instance,
+    // the counter is shared by all the clones of it.
+    #[inline(always)]
+    pub(super) fn counter(&self) -> ((usize, Vec<u8>), Arc<AtomicUsize>) {
+        ((self.idx, self.prefix.clone()), Arc::clone(&self.cnter))
+    }
+
+    // Imitate the behavior of 'BTreeMap<_>.first_key_value()',
+    // the storage order may differ from the `Ord` of `K`, so all the keys are scanned.
+    #[inline(always)]
+    pub(super) fn first(&self) -> Option<(K, V)>
+    where
+        K: Ord,
+    {
+        self.iter_keys()
+            .min()
+            .and_then(|k| self.get(&k).map(|v| (k, v)))
+    }
+
+    // Imitate the behavior of 'BTreeMap<_>.last_key_value()', see `first()`.
+    #[inline(always)]
+    pub(super) fn last(&self) -> Option<(K, V)>
+    where
+        K: Ord,
+    {
+        self.iter_keys()
+            .max()
+            .and_then(|k| self.get(&k).map(|v| (k, v)))
+    }
+
+    // Remove all the existing entries and write the new ones in one batch.
+    pub(super) fn replace_all<I>(&mut self, entries: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut batch = WriteBatch::default();
+        if let Some(upper) = prefix_upper_bound(&self.prefix) {
+            batch.delete_range(self.prefix.as_slice(), upper.as_slice());
+        } else {
+            for (k, _) in prefix_iter(self.idx, &self.prefix) {
+                batch.delete(k);
+            }
+        }
+
+        let watched = self.is_watched();
+        let mut written = vec![];
+        let mut keys = HashSet::new();
+        for (key, value) in entries {
+            let mut k = self.prefix.clone();
+            k.append(&mut bincode::serialize(&key).c(d!())?);
+            batch.put(&k, serde_json::to_vec(&value).c(d!())?);
+            keys.insert(k);
+            alt!(watched, written.push((key, value)));
+        }
+
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.store(keys.len(), Ordering::Relaxed);
+
+        // the old keys in the cold store are replaced too,
+        // and all the new keys are regarded as just accessed
+        if let Some(t) = self.eviction.as_ref() {
+            let mut t = t.lock().map_err(|e| eg!(e))?;
+            t.cold_store.replace_all(vec![]).c(d!())?;
+            t.last_access.clear();
+            t.by_op.clear();
+            keys.iter().for_each(|k| t.touch(k));
+        }
+
+        self.after_clear();
+        written.iter().for_each(|(k, v)| self.after_insert(k, v));
+
+        Ok(())
+    }
+
+    // Remove the existing keys in one batch, return the number of removed entries.
+    pub(super) fn remove_batch<I>(&mut self, keys: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let mut batch = WriteBatch::default();
+        let mut removed = HashSet::new();
+        let mut all_keys = vec![];
+
+        for key in keys {
+            let mut k = self.prefix.clone();
+            k.append(&mut bincode::serialize(&key).c(d!())?);
+            if !removed.contains(&k) && BNC[self.idx].get_pinned(&k).c(d!())?.is_some() {
+                batch.delete(&k);
+                removed.insert(k.clone());
+            }
+            all_keys.push((key, k));
+        }
+
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.fetch_sub(removed.len(), Ordering::Relaxed);
+
+        // the keys in the cold store are removed too
+        let mut n = removed.len();
+        for (key, k) in all_keys {
+            let in_cold = self.track_remove(&key, &k);
+            alt!(in_cold, n += 1);
+            if removed.remove(&k) || in_cold {
+                self.after_remove(&key);
+            }
+        }
+
+        Ok(n)
+    }
+
+    // Apply all the operations in one batch, in the given order.
+    pub(super) fn apply_patch(&mut self, patch: Vec<PatchEntry<K, V>>) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        // raw key => whether it exists after the operations so far
+        let mut exists = HashMap::new();
+        let mut cnter = self.cnter.load(Ordering::Relaxed);
+        let keep = self.is_watched() || self.is_tracked();
+        let mut written = vec![];
+
+        for entry in patch {
+            let (key, value) = match entry {
+                PatchEntry::Insert(key, value) => (key, Some(value)),
+                PatchEntry::Remove(key) => (key, None),
+            };
+
+            let mut k = self.prefix.clone();
+            k.append(&mut bincode::serialize(&key).c(d!())?);
+            let existed = match exists.get(&k) {
+                Some(existed) => *existed,
+                None => BNC[self.idx].get_pinned(&k).c(d!())?.is_some(),
+            };
+
+            if let Some(value) = value.as_ref() {
+                batch.put(&k, serde_json::to_vec(value).c(d!())?);
+                alt!(!existed, cnter += 1);
+                exists.insert(k.clone(), true);
+            } else {
+                batch.delete(&k);
+                alt!(existed, cnter -= 1);
+                exists.insert(k.clone(), false);
+            }
+            alt!(keep, written.push((key, k, value, existed)));
+        }
+
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.store(cnter, Ordering::Relaxed);
+
+        let mut inserted = vec![];
+        for (key, k, value, existed) in written {
+            if let Some(value) = value {
+                self.after_insert(&key, &value);
+                inserted.push((key, k));
+            } else {
+                // removing a non-existing key is not a write
+                let in_cold = self.track_remove(&key, &k);
+                if existed || in_cold {
+                    self.after_remove(&key);
+                }
+            }
+        }
+        // only the keys that exist after the patch are counted
+        self.track_writes(
+            inserted
+                .iter()
+                .filter(|(_, k)| exists.get(k).copied().unwrap_or(false))
+                .map(|(key, k)| (key, k.as_slice())),
+        );
+
+        Ok(())
+    }
+
+    // Load all the entries from one snapshot, sorted by the `Ord` of `K`,
+    // so they are consistent even if there are concurrent writes.
+    pub(super) fn snapshot_sorted_vec(&self) -> Result<Vec<(K, V)>>
+    where
+        K: Ord,
+    {
+        let snapshot = BNC[self.idx].snapshot();
+        let mut res = snapshot
+            .iterator_opt(
+                IteratorMode::From(&self.prefix, Direction::Forward),
+                prefix_read_opts(&self.prefix),
+            )
+            .map(|(k, v)| {
+                Ok((
+                    bincode::deserialize(&k[self.prefix.len()..]).c(d!())?,
+                    serde_json::from_slice(&v).c(d!())?,
+                ))
+            })
+            .collect::<Result<Vec<(K, V)>>>()?;
+        res.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(res)
+    }
+
     // Imitate the behavior of '.iter()'
     #[inline(always)]
     pub(super) fn iter(&self) -> MapxIter<'_, K, V> {
-        let i = BNC[self.idx].prefix_iterator(&self.prefix);
+        let i = prefix_iter(self.idx, &self.prefix);
 
         MapxIter {
             iter: i,
@@ -122,15 +1063,496 @@ where
         }
     }
 
+    // Skip the first `n` entries without decoding them,
+    // rocksdb can not seek to an offset, so they are still read.
+    pub(super) fn iter_skip(&self, n: usize) -> MapxIter<'_, K, V> {
+        let mut i = self.iter();
+        if 0 < n {
+            i.iter.nth(n - 1);
+        }
+        i
+    }
+
+    // The key at the position `n` in the storage order,
+    // only this key will be decoded.
+    pub(super) fn nth_key(&self, n: usize) -> Option<K> {
+        prefix_iter(self.idx, &self.prefix)
+            .nth(n)
+            .map(|(k, _)| pnk!(bincode::deserialize(&k[self.prefix.len()..])))
+    }
+
+    // The raw entries with the prefix of the instance,
+    // the iterator does not borrow the instance.
+    #[inline(always)]
+    pub(super) fn raw_iter(&self) -> (DBIterator<'static>, usize) {
+        (prefix_iter(self.idx, &self.prefix), self.prefix.len())
+    }
+
+    // Iterate over the keys only, the values are not decoded.
+    pub(super) fn iter_keys(&self) -> impl Iterator<Item = K> + '_ {
+        prefix_iter(self.idx, &self.prefix)
+            .map(move |(k, _)| pnk!(bincode::deserialize(&k[self.prefix.len()..])))
+    }
+
+    // Read at most `limit` entries after `start`(exclusive) in the storage order.
+    pub(super) fn scan_with_limit(
+        &self,
+        start: Option<&K>,
+        limit: usize,
+    ) -> Vec<(K, V)> {
+        let mut i = prefix_iter(self.idx, &self.prefix);
+        let start = start.map(|key| {
+            let mut k = self.prefix.clone();
+            k.append(&mut pnk!(bincode::serialize(key)));
+            k
+        });
+        if let Some(k) = start.as_ref() {
+            i.set_mode(IteratorMode::From(k, Direction::Forward));
+        }
+
+        i.skip_while(|(k, _)| Some(&k[..]) == start.as_deref())
+            .take(limit)
+            .map(|(k, v)| {
+                (
+                    pnk!(bincode::deserialize(&k[self.prefix.len()..])),
+                    pnk!(serde_json::from_slice(&v)),
+                )
+            })
+            .collect()
+    }
+
+    // Iterate over the entries whose serialized key starts with `key_prefix`.
+    pub(super) fn scan_prefix_bytes(
+        &self,
+        key_prefix: &[u8],
+    ) -> impl Iterator<Item = (K, V)> + '_ {
+        let mut prefix = self.prefix.clone();
+        prefix.extend_from_slice(key_prefix);
+
+        prefix_iter(self.idx, &prefix).map(move |(k, v)| {
+            (
+                pnk!(bincode::deserialize(&k[self.prefix.len()..])),
+                pnk!(serde_json::from_slice(&v)),
+            )
+        })
+    }
+
+    // Count the keys whose serialized form starts with `key_prefix`,
+    // neither the keys nor the values are decoded.
+    #[inline(always)]
+    pub(super) fn count_prefix_hits(&self, key_prefix: &[u8]) -> usize {
+        let mut prefix = self.prefix.clone();
+        prefix.extend_from_slice(key_prefix);
+        prefix_iter(self.idx, &prefix).count()
+    }
+
+    // Apply `f` to every entry, `None` means removing the entry,
+    // all the changes will be written in one batch.
+    pub(super) fn update_batch<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&K, V) -> Option<V>,
+    {
+        let mut batch = WriteBatch::default();
+        let mut removed = 0;
+        let keep = self.is_watched() || self.is_tracked();
+        let mut written = vec![];
+
+        for (k, v) in prefix_iter(self.idx, &self.prefix) {
+            let key = bincode::deserialize(&k[self.prefix.len()..]).c(d!())?;
+            let value = serde_json::from_slice(&v).c(d!())?;
+            let new_value = f(&key, value);
+            if let Some(new_value) = new_value.as_ref() {
+                batch.put(&k, serde_json::to_vec(new_value).c(d!())?);
+            } else {
+                batch.delete(&k);
+                removed += 1;
+            }
+            alt!(keep, written.push((key, k, new_value)));
+        }
+
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.fetch_sub(removed, Ordering::Relaxed);
+
+        for (key, k, value) in written.iter() {
+            if let Some(value) = value {
+                self.after_insert(key, value);
+            } else {
+                self.track_remove(key, k);
+                self.after_remove(key);
+            }
+        }
+        self.track_writes(
+            written
+                .iter()
+                .filter(|(_, _, v)| v.is_some())
+                .map(|(key, k, _)| (key, &k[..])),
+        );
+
+        Ok(())
+    }
+
+    // Remove the entries that `f` returns `false` for in one batch,
+    // `f` accepts the raw key and value without the prefix.
+    pub(super) fn retain_raw<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&[u8], &[u8]) -> Result<bool>,
+    {
+        let mut batch = WriteBatch::default();
+        let mut removed = 0;
+        let keep = self.is_watched() || self.is_tracked();
+        let mut removed_keys = vec![];
+
+        for (k, v) in prefix_iter(self.idx, &self.prefix) {
+            if !f(&k[self.prefix.len()..], &v).c(d!())? {
+                batch.delete(&k);
+                removed += 1;
+                alt!(keep, removed_keys.push(k));
+            }
+        }
+
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.fetch_sub(removed, Ordering::Relaxed);
+
+        // the keys are decoded only if the removals are watched or tracked
+        for k in removed_keys {
+            let key = bincode::deserialize(&k[self.prefix.len()..]).c(d!())?;
+            self.track_remove(&key, &k);
+            self.after_remove(&key);
+        }
+
+        Ok(())
+    }
+
+    // Keep the entries whose key satisfies `f`, the values are not decoded.
+    #[inline(always)]
+    pub(super) fn retain_keys<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        self.retain_raw(|k, _| bincode::deserialize(k).c(d!()).map(|key| f(&key)))
+            .c(d!())
+    }
+
+    // Keep the entries whose value satisfies `f`, the keys are not decoded.
+    #[inline(always)]
+    pub(super) fn retain_values<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&V) -> bool,
+    {
+        self.retain_raw(|_, v| serde_json::from_slice(v).c(d!()).map(|value| f(&value)))
+            .c(d!())
+    }
+
+    // Remove and return the entries satisfying `pred` in one pass,
+    // all the deletions are written in one batch.
+    pub(super) fn take_where<F>(&mut self, mut pred: F) -> Result<Vec<(K, V)>>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut batch = WriteBatch::default();
+        let mut res = vec![];
+
+        for (k, v) in prefix_iter(self.idx, &self.prefix) {
+            let key = bincode::deserialize(&k[self.prefix.len()..]).c(d!())?;
+            let value = serde_json::from_slice(&v).c(d!())?;
+            if pred(&key, &value) {
+                batch.delete(k);
+                res.push((key, value));
+            }
+        }
+
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.fetch_sub(res.len(), Ordering::Relaxed);
+
+        res.iter().for_each(|(k, _)| self.after_remove(k));
+
+        Ok(res)
+    }
+
+    // Remove and return the `n` smallest entries by the `Ord` of `K`,
+    // or the `n` largest ones if `largest` is true,
+    // the result is sorted by the `Ord` of `K`.
+    //
+    // The storage order may differ from the `Ord` of `K`,
+    // so all the keys are scanned, and at most `n` entries are kept in memory.
+    pub(super) fn take_n_by_ord(
+        &mut self,
+        n: usize,
+        largest: bool,
+    ) -> Result<Vec<(K, V)>>
+    where
+        K: Ord,
+    {
+        if 0 == n {
+            return Ok(vec![]);
+        }
+
+        let mut kept = BTreeMap::new();
+        for (k, v) in prefix_iter(self.idx, &self.prefix) {
+            let key: K = bincode::deserialize(&k[self.prefix.len()..]).c(d!())?;
+            kept.insert(key, (k, v));
+            if kept.len() > n {
+                if largest {
+                    kept.pop_first();
+                } else {
+                    kept.pop_last();
+                }
+            }
+        }
+
+        let mut batch = WriteBatch::default();
+        kept.values().for_each(|(k, _)| batch.delete(k));
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.fetch_sub(kept.len(), Ordering::Relaxed);
+
+        kept.into_iter()
+            .map(|(key, (k, v))| {
+                // stop tracking the taken keys
+                self.track_remove(&key, &k);
+                self.after_remove(&key);
+                Ok((key, serde_json::from_slice(&v).c(d!())?))
+            })
+            .collect()
+    }
+
+    // Register a merge operator for this instance,
+    // it can be replaced only by an operator with the same name,
+    // the name is saved in the meta, so it is checked across restarts.
+    #[cfg(feature = "merge-operator")]
+    pub(super) fn set_merge_operator(&self, name: &str, op: MergeOp) -> Result<()> {
+        let mut ops = MERGE_OPS.write().map_err(|e| eg!(e))?;
+
+        let saved = self
+            .read_meta(MERGE_OPERATOR)
+            .c(d!())?
+            .map(String::from_utf8);
+        let key = (self.idx, self.prefix.clone());
+        let registered = ops.get(&key).map(|(n, _)| n.clone());
+        if let Some(n) = saved.transpose().c(d!())?.or(registered) {
+            if n != name {
+                return Err(eg!(format!(
+                    "merge operator '{}' has been registered for '{}'",
+                    n, self.path
+                )));
+            }
+        }
+
+        self.write_meta(MERGE_OPERATOR, name.as_bytes()).c(d!())?;
+        ops.insert(key, (name.to_owned(), op));
+        Ok(())
+    }
+
+    #[cfg(feature = "merge-operator")]
+    pub(super) fn merge(&mut self, key: K, partial_value: V) -> Result<()> {
+        if !MERGE_OPS
+            .read()
+            .map_err(|e| eg!(e))?
+            .contains_key(&(self.idx, self.prefix.clone()))
+        {
+            return Err(eg!(format!(
+                "no merge operator has been registered for '{}'",
+                self.path
+            )));
+        }
+
+        let mut k = self.prefix.clone();
+        k.append(&mut bincode::serialize(&key).c(d!())?);
+        let v = serde_json::to_vec(&partial_value).c(d!())?;
+        let exists = BNC[self.idx].get_pinned(&k).c(d!())?.is_some();
+
+        BNC[self.idx].merge(k, v).c(d!())?;
+
+        if !exists {
+            self.cnter.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // the merged value is read back only if the writes are watched
+        if self.is_watched() {
+            if let Some(value) = self.get(&key) {
+                self.after_insert(&key, &value);
+            }
+        }
+
+        Ok(())
+    }
+
     pub(super) fn contains_key(&self, key: &K) -> bool {
         let mut k = self.prefix.clone();
         k.append(&mut pnk!(bincode::serialize(key)));
-        pnk!(BNC[self.idx].get_pinned(k)).is_some()
+        if pnk!(BNC[self.idx].get_pinned(&k)).is_some() {
+            self.touch(&k);
+            true
+        } else {
+            self.get_cold(key).is_some()
+        }
+    }
+
+    // Check the existence of all the keys with one `multi_get`.
+    pub(super) fn contains_keys(&self, keys: &[K]) -> Vec<bool> {
+        let raw_keys = keys.iter().map(|key| {
+            let mut k = self.prefix.clone();
+            k.append(&mut pnk!(bincode::serialize(key)));
+            k
+        });
+        BNC[self.idx]
+            .multi_get(raw_keys)
+            .into_iter()
+            .zip(keys.iter())
+            .map(|(v, key)| pnk!(v).is_some() || self.get_cold(key).is_some())
+            .collect()
     }
 
     pub(super) fn remove(&mut self, key: &K) -> Option<V> {
+        let cold_v = self.get_cold(key);
         self.unset_value(key)
             .map(|v| pnk!(serde_json::from_slice(&v)))
+            .or(cold_v)
+    }
+
+    // Put the value to `dest` and delete `src` in one batch,
+    // `src` may be found in the cold store.
+    pub(super) fn move_entry(&mut self, src: &K, dest: K) -> Result<Option<V>> {
+        let mut src_k = self.prefix.clone();
+        src_k.append(&mut bincode::serialize(src).c(d!())?);
+        let (v, value, src_exists): (_, V, _) =
+            match BNC[self.idx].get(&src_k).c(d!())? {
+                Some(v) => {
+                    let value = serde_json::from_slice(&v).c(d!())?;
+                    (v, value, true)
+                }
+                None => match self.get_cold(src) {
+                    Some(value) => (serde_json::to_vec(&value).c(d!())?, value, false),
+                    None => return Ok(None),
+                },
+            };
+
+        if src == &dest {
+            return Ok(Some(value));
+        }
+
+        let mut dest_k = self.prefix.clone();
+        dest_k.append(&mut bincode::serialize(&dest).c(d!())?);
+        let dest_exists = BNC[self.idx].get_pinned(&dest_k).c(d!())?.is_some();
+
+        let mut batch = WriteBatch::default();
+        batch.put(&dest_k, v);
+        batch.delete(&src_k);
+        BNC[self.idx].write(batch).c(d!())?;
+
+        match (src_exists, dest_exists) {
+            (true, true) => {
+                self.cnter.fetch_sub(1, Ordering::Relaxed);
+            }
+            (false, false) => {
+                self.cnter.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+
+        self.track_remove(src, &src_k);
+        self.track_write(&dest, &dest_k);
+
+        self.after_remove(src);
+        self.after_insert(&dest, &value);
+
+        Ok(Some(value))
+    }
+
+    // Read the raw value, and write the updated one back in a batch,
+    // the write fails if the raw value has been changed in the meantime,
+    // see `atomic_rmw` for the limits of this check.
+    pub(super) fn update_in_place<F>(&mut self, key: &K, f: F) -> Result<bool>
+    where
+        F: FnOnce(&mut V),
+    {
+        let mut k = self.prefix.clone();
+        k.append(&mut bincode::serialize(key).c(d!())?);
+
+        let old_raw = BNC[self.idx].get(&k).c(d!())?;
+        let mut value: V = match old_raw.as_ref() {
+            Some(v) => serde_json::from_slice(v).c(d!())?,
+            None => match self.get_cold(key) {
+                Some(mut v) => {
+                    // let `set_value` move it back to the primary store
+                    f(&mut v);
+                    self.set_value(key.clone(), v);
+                    return Ok(true);
+                }
+                None => return Ok(false),
+            },
+        };
+        f(&mut value);
+
+        let mut batch = WriteBatch::default();
+        batch.put(&k, serde_json::to_vec(&value).c(d!())?);
+
+        {
+            let _lk = RMW_LOCKS[hash(&(self.idx, &k)) % RMW_LOCK_NUM]
+                .lock()
+                .map_err(|e| eg!(e))?;
+            if BNC[self.idx].get(&k).c(d!())? != old_raw {
+                return Err(eg!("conflict: the value has been changed by others"));
+            }
+            BNC[self.idx].write(batch).c(d!())?;
+        }
+
+        self.track_write(key, &k);
+        self.after_insert(key, &value);
+
+        Ok(true)
+    }
+
+    // Read the raw value, and write the result of `f` in a batch,
+    // the raw value is read again under a striped lock before the write,
+    // and the write fails if it differs from the first read;
+    // the plain writes do not take the lock, and an ABA change is not detected.
+    pub(super) fn atomic_rmw<F>(&mut self, key: K, f: F) -> Result<Option<V>>
+    where
+        F: FnOnce(Option<V>) -> Option<V>,
+    {
+        let mut k = self.prefix.clone();
+        k.append(&mut bincode::serialize(&key).c(d!())?);
+
+        let old_raw = BNC[self.idx].get(&k).c(d!())?;
+        let old_v = match old_raw.as_ref() {
+            Some(v) => Some(serde_json::from_slice(v).c(d!())?),
+            None => self.get_cold(&key),
+        };
+        let new_v = f(old_v.clone());
+
+        let mut batch = WriteBatch::default();
+        match new_v.as_ref() {
+            Some(v) => batch.put(&k, serde_json::to_vec(v).c(d!())?),
+            None => batch.delete(&k),
+        }
+
+        {
+            let _lk = RMW_LOCKS[hash(&(self.idx, &k)) % RMW_LOCK_NUM]
+                .lock()
+                .map_err(|e| eg!(e))?;
+            if BNC[self.idx].get(&k).c(d!())? != old_raw {
+                return Err(eg!("conflict: the value has been changed by others"));
+            }
+            BNC[self.idx].write(batch).c(d!())?;
+        }
+
+        if let Some(value) = new_v {
+            if old_raw.is_none() {
+                self.cnter.fetch_add(1, Ordering::Relaxed);
+            }
+            self.track_write(&key, &k);
+            self.after_insert(&key, &value);
+        } else {
+            let in_cold = self.track_remove(&key, &k);
+            if old_raw.is_some() {
+                self.cnter.fetch_sub(1, Ordering::Relaxed);
+            }
+            if old_raw.is_some() || in_cold {
+                self.after_remove(&key);
+            }
+        }
+
+        Ok(old_v)
     }
 
     pub(super) fn unset_value(&mut self, key: &K) -> Option<DBPinnableSlice> {
@@ -138,10 +1560,16 @@ where
         k.append(&mut pnk!(bincode::serialize(&key)));
         let old_v = pnk!(BNC[self.idx].get_pinned(&k));
 
-        pnk!(BNC[self.idx].delete(k));
+        pnk!(BNC[self.idx].delete(&k));
+
+        let in_cold = self.track_remove(key, &k);
 
         if old_v.is_some() {
-            self.cnter -= 1;
+            self.cnter.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        if old_v.is_some() || in_cold {
+            self.after_remove(key);
         }
 
         old_v
@@ -152,6 +1580,82 @@ where
 // End of the self-implementation of backend::Mapx //
 /////////////////////////////////////////////////////
 
+// `num_deletions / (num_entries + num_deletions)`,
+// `num_entries` is the number of the live entries.
+#[inline(always)]
+pub(super) fn fragmentation(num_entries: u64, num_deletions: u64) -> f64 {
+    let total = num_entries + num_deletions;
+    alt!(0 == total, 0.0, num_deletions as f64 / total as f64)
+}
+
+// Set the memtable options of a db instance.
+fn set_write_buffer(idx: usize, size: u64, num: u32) -> Result<()> {
+    BNC[idx]
+        .set_options(&[
+            ("write_buffer_size", size.to_string().as_str()),
+            ("max_write_buffer_number", num.to_string().as_str()),
+        ])
+        .c(d!())
+}
+
+// Read the raw keys with the block cache filled, the results are discarded.
+fn warm_up_raw(idx: usize, keys: Vec<Vec<u8>>) {
+    let mut opts = ReadOptions::default();
+    opts.fill_cache(true);
+    BNC[idx].multi_get_opt(keys, &opts);
+}
+
+// Tracks the last access of the keys in the primary store,
+// see `Mapx::set_eviction_policy()`.
+#[derive(Debug)]
+struct AccessTracker<K, V>
+where
+    K: Clone + Eq + PartialEq + Hash + Serialize + DeserializeOwned + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    cold_store: Mapx<K, V>,
+    access_threshold: u64,
+    // the number of writes so far
+    op: u64,
+    // raw key => the `op` of its last access
+    last_access: HashMap<Vec<u8>, u64>,
+    // (the `op` of the last access, raw key)
+    by_op: BTreeSet<(u64, Vec<u8>)>,
+}
+
+impl<K, V> AccessTracker<K, V>
+where
+    K: Clone + Eq + PartialEq + Hash + Serialize + DeserializeOwned + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    fn touch(&mut self, k: &[u8]) {
+        if let Some(old_op) = self.last_access.insert(k.to_vec(), self.op) {
+            self.by_op.remove(&(old_op, k.to_vec()));
+        }
+        self.by_op.insert((self.op, k.to_vec()));
+    }
+
+    fn untrack(&mut self, k: &[u8]) {
+        if let Some(old_op) = self.last_access.remove(k) {
+            self.by_op.remove(&(old_op, k.to_vec()));
+        }
+    }
+
+    // Stop tracking the keys which should be evicted, and return them.
+    fn pop_expired(&mut self) -> Vec<Vec<u8>> {
+        let mut res = vec![];
+        while let Some((op, _)) = self.by_op.first() {
+            if self.op - op < self.access_threshold {
+                break;
+            }
+            let (_, k) = pnk!(self.by_op.pop_first());
+            self.last_access.remove(&k);
+            res.push(k);
+        }
+        res
+    }
+}
+
 ///////////////////////////////////////////////////////////
 // Begin of the implementation of Iter for backend::Mapx //
 /*********************************************************/
@@ -0,0 +1,127 @@
+//!
+//! # A builder of Mapx
+//!
+
+use super::{backend, Mapx};
+use crate::helper::meta_exists;
+use ruc::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, hash::Hash, marker::PhantomData};
+
+/// Build a Mapx step by step, see `Mapx::builder()`.
+///
+/// **NOTE**: there is no `compression()` or `cache_size()`,
+/// the lz4 compression and the block cache are set when the underlying db instances
+/// are opened, and every db instance is shared by many collections(see the storage
+/// notes of `mapx`), so they can not be set for a single instance.
+#[derive(Debug, Clone)]
+pub struct MapxBuilder<K, V> {
+    path: String,
+    create_if_missing: bool,
+    namespace_prefix: Option<Vec<u8>>,
+    metrics: bool,
+    change_log: Option<String>,
+    _pd: PhantomData<(K, V)>,
+}
+
+impl<K, V> MapxBuilder<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    /// Create a builder with a unique path and the default options.
+    #[inline(always)]
+    pub fn new() -> Self {
+        MapxBuilder {
+            path: crate::unique_path!(),
+            create_if_missing: true,
+            namespace_prefix: None,
+            metrics: false,
+            change_log: None,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Set the path of the instance.
+    #[inline(always)]
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_owned();
+        self
+    }
+
+    /// If `false`, `build()` fails when there is no instance at the path,
+    /// default to `true`.
+    #[inline(always)]
+    pub fn create_if_missing(mut self, create_if_missing: bool) -> Self {
+        self.create_if_missing = create_if_missing;
+        self
+    }
+
+    /// See `Mapx::with_namespace_prefix()`.
+    #[inline(always)]
+    pub fn namespace_prefix(mut self, prefix: Vec<u8>) -> Self {
+        self.namespace_prefix = Some(prefix);
+        self
+    }
+
+    /// See `Mapx::with_metrics()`, default to `false`.
+    #[inline(always)]
+    pub fn metrics(mut self, enable: bool) -> Self {
+        self.metrics = enable;
+        self
+    }
+
+    /// See `Mapx::with_change_log()`.
+    #[inline(always)]
+    pub fn change_log(mut self, log_path: &str) -> Self {
+        self.change_log = Some(log_path.to_owned());
+        self
+    }
+
+    /// Create the instance with the options.
+    pub fn build(self) -> Result<Mapx<K, V>> {
+        if !self.create_if_missing && !meta_exists(&self.path) {
+            return Err(eg!(format!("'{}' does not exist", self.path)));
+        }
+
+        let mut in_disk = backend::Mapx::load_or_create_in_namespace(
+            &self.path,
+            self.namespace_prefix.as_deref().unwrap_or_default(),
+        )
+        .c(d!())?;
+        if self.metrics {
+            in_disk.enable_metrics();
+        }
+        if let Some(log_path) = self.change_log.as_ref() {
+            in_disk.set_change_log(log_path).c(d!())?;
+        }
+
+        Ok(Mapx { in_disk })
+    }
+}
+
+impl<K, V> Default for MapxBuilder<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
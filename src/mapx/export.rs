@@ -0,0 +1,244 @@
+//!
+//! # Streaming export/import and debug dump of Mapx
+//!
+
+use super::Mapx;
+use ruc::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt,
+    hash::Hash,
+    io::{self, BufRead, BufReader, Read, Write},
+    mem::size_of,
+};
+
+// The number of entries written in one batch by `load_from`.
+const LOAD_BATCH_SIZE: usize = 4096;
+
+// The max size of an entry of `ExportFormat::BinaryCoded` accepted by `load_from`,
+// the length header is untrusted input.
+const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The formats supported by `Mapx::pipe_to()` and `Mapx::load_from()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per line: `{"key":...,"value":...}`
+    NdJson,
+    /// A `key,value` header, then one line per entry,
+    /// both fields are JSON texts quoted as CSV strings
+    Csv,
+    /// Each entry is a bincode-encoded `(key, value)`,
+    /// prefixed by its length as a little-endian `u64`
+    BinaryCoded,
+}
+
+const CSV_HEADER: &str = "key,value";
+
+#[derive(Serialize, Deserialize)]
+struct ExportEntry<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<K, V> Mapx<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    /// Write the entries to `writer` in storage(encoded byte) order,
+    /// each entry is written as soon as it is read,
+    /// return the number of written entries.
+    pub fn pipe_to<W: Write>(
+        &self,
+        writer: &mut W,
+        format: ExportFormat,
+    ) -> Result<usize> {
+        if ExportFormat::Csv == format {
+            writeln!(writer, "{}", CSV_HEADER).c(d!())?;
+        }
+
+        let mut cnt = 0;
+        for (key, value) in self.iter() {
+            match format {
+                ExportFormat::NdJson => {
+                    serde_json::to_writer(&mut *writer, &ExportEntry { key, value })
+                        .c(d!())?;
+                    writer.write_all(b"\n").c(d!())?;
+                }
+                ExportFormat::Csv => {
+                    let k = serde_json::to_string(&key).c(d!())?;
+                    let v = serde_json::to_string(&value).c(d!())?;
+                    writeln!(writer, "{},{}", csv_quote(&k), csv_quote(&v)).c(d!())?;
+                }
+                ExportFormat::BinaryCoded => {
+                    let bytes = bincode::serialize(&(key, value)).c(d!())?;
+                    writer
+                        .write_all(&(bytes.len() as u64).to_le_bytes())
+                        .c(d!())?;
+                    writer.write_all(&bytes).c(d!())?;
+                }
+            }
+            cnt += 1;
+        }
+
+        writer.flush().c(d!())?;
+        Ok(cnt)
+    }
+
+    /// Create an instance at `path` with the entries
+    /// written by `pipe_to()` in the same format.
+    ///
+    /// The entries are written in batches,
+    /// so only a part of them will be kept in memory.
+    ///
+    /// An error will be returned if an entry of `ExportFormat::BinaryCoded`
+    /// is truncated or larger than 64 MiB.
+    pub fn load_from<R: Read>(
+        reader: &mut R,
+        format: ExportFormat,
+        path: &str,
+    ) -> Result<Self> {
+        let mut db = Mapx::new(path).c(d!())?;
+        let mut entries = Vec::with_capacity(LOAD_BATCH_SIZE);
+
+        let flush = |db: &mut Mapx<K, V>, entries: &mut Vec<(K, V)>| {
+            db.in_disk.set_value_batch(entries.drain(..)).c(d!())
+        };
+
+        match format {
+            ExportFormat::NdJson | ExportFormat::Csv => {
+                let mut lines = BufReader::new(reader).lines();
+                if ExportFormat::Csv == format {
+                    match lines.next().transpose().c(d!())? {
+                        Some(line) if CSV_HEADER == line.trim_end() => {}
+                        None => return Ok(db),
+                        _ => return Err(eg!("invalid csv header")),
+                    }
+                }
+                for line in lines {
+                    let line = line.c(d!())?;
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let entry = if ExportFormat::NdJson == format {
+                        let e: ExportEntry<K, V> =
+                            serde_json::from_str(&line).c(d!())?;
+                        (e.key, e.value)
+                    } else {
+                        let (k, v) = csv_split(&line).c(d!(line))?;
+                        (
+                            serde_json::from_str(&k).c(d!())?,
+                            serde_json::from_str(&v).c(d!())?,
+                        )
+                    };
+                    entries.push(entry);
+                    if LOAD_BATCH_SIZE == entries.len() {
+                        flush(&mut db, &mut entries).c(d!())?;
+                    }
+                }
+            }
+            ExportFormat::BinaryCoded => loop {
+                // only a missing header means the end,
+                // a partial one is a truncated entry
+                let mut len = vec![];
+                reader
+                    .by_ref()
+                    .take(size_of::<u64>() as u64)
+                    .read_to_end(&mut len)
+                    .c(d!())?;
+                if len.is_empty() {
+                    break;
+                }
+                let len = u64::from_le_bytes(
+                    len.try_into().map_err(|_| eg!("truncated entry length"))?,
+                );
+                if MAX_ENTRY_SIZE < len {
+                    return Err(eg!(format!("entry size({}) exceeds the limit", len)));
+                }
+                let mut bytes = vec![];
+                reader.by_ref().take(len).read_to_end(&mut bytes).c(d!())?;
+                if len != bytes.len() as u64 {
+                    return Err(eg!("truncated entry"));
+                }
+                entries.push(bincode::deserialize(&bytes).c(d!())?);
+                if LOAD_BATCH_SIZE == entries.len() {
+                    flush(&mut db, &mut entries).c(d!())?;
+                }
+            },
+        }
+
+        flush(&mut db, &mut entries).c(d!())?;
+        Ok(db)
+    }
+
+    /// Print all the entries to stderr with a header line of the path and the length,
+    /// for debugging only.
+    pub fn debug_dump(&self) {
+        pnk!(self.debug_dump_to(&mut io::stderr()));
+    }
+
+    /// Same as `debug_dump()`, but write to `writer`,
+    /// each entry is formatted as `{:?} => {:?}` in one line.
+    pub fn debug_dump_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writeln!(
+            writer,
+            "Mapx at '{}', {} entries:",
+            self.get_path(),
+            self.len()
+        )
+        .c(d!())?;
+        for (k, v) in self.iter() {
+            writeln!(writer, "{:?} => {:?}", k, v).c(d!())?;
+        }
+        writer.flush().c(d!())
+    }
+}
+
+#[inline(always)]
+fn csv_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+// Split a line of two quoted fields, JSON texts contain no raw newlines,
+// so a line is always a complete record.
+fn csv_split(line: &str) -> Result<(String, String)> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+
+    while fields.len() < 2 {
+        if Some('"') != chars.next() {
+            return Err(eg!("a quoted field is expected"));
+        }
+        let mut field = String::new();
+        loop {
+            match chars.next() {
+                Some('"') if Some(&'"') == chars.peek() => {
+                    chars.next();
+                    field.push('"');
+                }
+                Some('"') => break,
+                Some(c) => field.push(c),
+                None => return Err(eg!("unterminated field")),
+            }
+        }
+        fields.push(field);
+
+        match chars.next() {
+            Some(',') if 1 == fields.len() => {}
+            None if 2 == fields.len() => {}
+            _ => return Err(eg!("invalid field separator")),
+        }
+    }
+
+    let v = fields.pop().c(d!())?;
+    let k = fields.pop().c(d!())?;
+    Ok((k, v))
+}
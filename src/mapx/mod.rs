@@ -3,21 +3,56 @@
 //!
 //! This module is non-invasive to external code except the `new` method.
 //!
+//! # Storage Notes
+//!
+//! - The collections are hashed into a few underlying db instances by their paths,
+//!   so the db-level statistics and operations(e.g. `approximate_size_bytes()`)
+//!   cover all the collections in the same db instance.
+//! - The keys are stored in the order of their bincode-serialized bytes,
+//!   which may differ from the `Ord` of `K`, so the methods working on
+//!   the `Ord` of `K`(e.g. `keys_after()`) scan all the keys of the collection.
+//!
 
 mod backend;
+mod builder;
+mod export;
+mod set;
 #[cfg(test)]
 mod test;
 
+pub use builder::MapxBuilder;
+pub use export::ExportFormat;
+pub use set::MapxSet;
+
+#[cfg(not(feature = "serialize-full"))]
 use crate::serde::{CacheMeta, CacheVisitor};
+use crate::{
+    helper::{SCHEMA_TYPES, SCHEMA_VERSION},
+    Vecx,
+};
+use rand::Rng;
+use rocksdb::{DBIterator, WriteBatch};
 use ruc::*;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-    cmp::Ordering,
+    any::type_name,
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
     fmt,
+    fs::File,
     hash::Hash,
-    iter::Iterator,
+    io::{BufRead, BufReader},
+    iter::{FusedIterator, Iterator, Sum},
     mem::ManuallyDrop,
-    ops::{Deref, DerefMut},
+    ops::{Add, AddAssign, Deref, DerefMut, RangeBounds},
+    panic,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering as AtomicOrdering},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
 };
 
 /// To solve the problem of unlimited memory usage,
@@ -39,6 +74,205 @@ where
     in_disk: backend::Mapx<K, V>,
 }
 
+/// The write events of a Mapx, see `Mapx::subscribe()`.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapxEvent<K, V> {
+    /// A key has been inserted or updated
+    Inserted(K, V),
+    /// An existing key has been removed
+    Removed(K),
+    /// All the entries have been removed, see `Mapx::atomic_swap_all()`
+    Cleared,
+}
+
+/// The change events passed to the observers, see `Mapx::register_observer()`.
+#[cfg(feature = "observe")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapxChangeEvent<K, V> {
+    /// A key has been inserted or updated
+    KeyInserted {
+        /// The inserted key
+        key: K,
+        /// The new value of the key
+        value: V,
+    },
+    /// An existing key has been removed
+    KeyRemoved {
+        /// The removed key
+        key: K,
+    },
+    /// All the entries have been removed, see `Mapx::atomic_swap_all()`
+    Cleared,
+}
+
+// The operation type of a change log entry.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ChangeOp {
+    Insert,
+    Remove,
+    Clear,
+}
+
+// One line of the change log, see `Mapx::with_change_log()`.
+#[derive(Debug, Serialize, Deserialize)]
+struct ChangeLogEntry<K, V> {
+    op: ChangeOp,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<K>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<V>,
+    ts: u64,
+}
+
+/// One write operation of a patch, see `Mapx::apply_patch()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatchEntry<K, V> {
+    /// Insert or update a key
+    Insert(K, V),
+    /// Remove a key
+    Remove(K),
+}
+
+/// A value with the time it was written, see `Mapx::get_all_versions()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionedEntry<V> {
+    /// The value of this version
+    pub value: V,
+    /// The (approximate) time when this version was written
+    pub timestamp: SystemTime,
+}
+
+/// The result of `Mapx::validate_invariants()`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The value of the length counter
+    pub len: usize,
+    /// The real number of entries in the db
+    pub entries: usize,
+    /// The number of entries whose key and value are both decodable
+    pub valid_entries: usize,
+    /// The number of entries whose key can not be decoded
+    pub invalid_keys: usize,
+    /// The number of entries whose value can not be decoded
+    pub invalid_values: usize,
+}
+
+impl ValidationReport {
+    /// Check if no problem has been found.
+    #[inline(always)]
+    pub fn is_valid(&self) -> bool {
+        self.len == self.entries && self.entries == self.valid_entries
+    }
+}
+
+/// The memory usage of the db instance of a Mapx,
+/// see `Mapx::estimate_memory_overhead()`.
+///
+/// **NOTE**: all the fields are shared by the collections in the same db instance,
+/// and the block cache may also be shared by the other db instances.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryBreakdown {
+    /// The memory used by the block cache
+    pub block_cache_bytes: u64,
+    /// The memory used by the unflushed memtables(write buffers)
+    pub write_buffer_bytes: u64,
+    /// The memory used to read the SST files, e.g. indexes and filters
+    pub table_readers_bytes: u64,
+    /// The memory used by the flushed memtables
+    /// which are still pinned by iterators or snapshots
+    pub misc_bytes: u64,
+}
+
+impl MemoryBreakdown {
+    /// The sum of all the fields.
+    #[inline(always)]
+    pub fn total_bytes(&self) -> u64 {
+        self.block_cache_bytes
+            + self.write_buffer_bytes
+            + self.table_readers_bytes
+            + self.misc_bytes
+    }
+}
+
+/// The handle of a background sync, see `Mapx::sync_continuously()`.
+///
+/// The sync stops when the handle is dropped.
+#[derive(Debug)]
+pub struct SyncHandle {
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl SyncHandle {
+    /// Stop the sync, and wait for the running round to finish.
+    #[inline(always)]
+    pub fn stop(mut self) {
+        self.stop_and_join();
+    }
+
+    fn stop_and_join(&mut self) {
+        self.stopped.store(true, AtomicOrdering::Relaxed);
+        if let Some(w) = self.worker.take() {
+            w.thread().unpark();
+            omit!(w.join());
+        }
+    }
+}
+
+impl Drop for SyncHandle {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
+
+/// The hot/cold tiering policy of a Mapx, see `Mapx::set_eviction_policy()`.
+#[derive(Debug)]
+pub struct EvictionPolicy<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    /// Where the cold entries are moved to
+    pub cold_store: Mapx<K, V>,
+    /// A key will be moved to `cold_store`
+    /// after this number of writes since its last access
+    pub access_threshold: usize,
+}
+
+/// The operation counters of a Mapx, see `Mapx::with_metrics()`.
+#[derive(Debug, Default)]
+pub struct MapxMetrics {
+    /// The number of `insert` and `set_value` calls
+    pub insert_count: AtomicU64,
+    /// The number of `remove` and `unset_value` calls
+    pub remove_count: AtomicU64,
+    /// The number of `get` calls that found a value
+    pub get_hit_count: AtomicU64,
+    /// The number of `get` calls that found nothing
+    pub get_miss_count: AtomicU64,
+    /// The number of `contains_key` calls
+    pub contains_key_count: AtomicU64,
+    /// The number of iterators created by `iter`
+    pub iter_count: AtomicU64,
+}
+
+impl MapxMetrics {
+    #[inline(always)]
+    fn incr(cnter: &AtomicU64) {
+        cnter.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+}
+
 ///////////////////////////////////////////////
 // Begin of the self-implementation for Mapx //
 /*********************************************/
@@ -63,17 +297,433 @@ where
         Ok(Mapx { in_disk })
     }
 
+    /// Create an instance with a schema version,
+    /// which is saved with the instance.
+    ///
+    /// If the saved version is older than `version`,
+    /// `migrator(db, saved_version)` will be called before saving the new version;
+    /// an existing non-empty instance without a saved version is regarded as version `0`.
+    ///
+    /// An error will be returned if the saved version is newer than `version`.
+    pub fn open_with_version<F>(path: &str, version: u32, migrator: F) -> Result<Self>
+    where
+        F: Fn(&mut Mapx<K, V>, u32) -> Result<()>,
+    {
+        let mut db = Self::new(path).c(d!())?;
+
+        let saved = match db.in_disk.read_meta(SCHEMA_VERSION).c(d!())? {
+            Some(bytes) => u32::from_le_bytes(
+                bytes[..]
+                    .try_into()
+                    .map_err(|_| eg!("invalid schema version"))?,
+            ),
+            None if db.is_empty() => version,
+            None => 0,
+        };
+
+        match saved.cmp(&version) {
+            Ordering::Greater => {
+                return Err(eg!(format!(
+                    "the saved version({}) is newer than {}",
+                    saved, version
+                )));
+            }
+            Ordering::Less => migrator(&mut db, saved).c(d!())?,
+            Ordering::Equal => {}
+        }
+
+        db.in_disk
+            .write_meta(SCHEMA_VERSION, &version.to_le_bytes())
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Check if the types of the keys and the values are the same as before,
+    /// the type names are saved with the instance on the first check.
+    ///
+    /// **NOTE**: the names come from `std::any::type_name`,
+    /// so renaming or moving a type is also regarded as a mismatch.
+    pub fn assert_schema(&self) -> Result<()> {
+        let schema = format!("{}\n{}", type_name::<K>(), type_name::<V>());
+
+        match self.in_disk.read_meta(SCHEMA_TYPES).c(d!())? {
+            Some(saved) if saved == schema.as_bytes() => Ok(()),
+            Some(saved) => {
+                let saved = String::from_utf8_lossy(&saved);
+                let mut saved = saved.split('\n');
+                Err(eg!(format!(
+                    "schema mismatch: expected <{}, {}>, found <{}, {}>",
+                    saved.next().unwrap_or_default(),
+                    saved.next().unwrap_or_default(),
+                    type_name::<K>(),
+                    type_name::<V>()
+                )))
+            }
+            None => self
+                .in_disk
+                .write_meta(SCHEMA_TYPES, schema.as_bytes())
+                .c(d!()),
+        }
+    }
+
+    /// Create an instance at `path` with the entries of `iter`,
+    /// which are written in one batch.
+    ///
+    /// Like 'BTreeMap::from_iter(...)', the last value wins for duplicate keys.
+    pub fn from_iter_at_path<I>(iter: I, path: &str) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut db = Self::new(path).c(d!())?;
+        db.in_disk.set_value_batch(iter).c(d!()).map(|_| db)
+    }
+
+    /// Create a builder of an instance,
+    /// e.g. `Mapx::builder().path("...").metrics(true).build()`,
+    /// the db-level options are not included, see `MapxBuilder`.
+    #[inline(always)]
+    pub fn builder() -> MapxBuilder<K, V> {
+        MapxBuilder::new()
+    }
+
+    /// Create an instance whose keys are stored with `prefix` prepended,
+    /// so multiple instances can share one path(and the same db instance)
+    /// with isolated key spaces, an empty `prefix` is rejected.
+    ///
+    /// The keys of all the namespaces are stored apart from the keys of
+    /// `Mapx::new(path)`, and each `prefix` is stored with its length,
+    /// so no instance can see the keys of another one.
+    ///
+    /// **NOTE**:
+    /// - a namespaced instance can not be renamed, neither can the plain instance
+    ///   on the path if the data need to be moved to another db instance
+    /// - the extra meta, such as the schema version, is kept for each namespace
+    #[inline(always)]
+    pub fn with_namespace_prefix(path: &str, prefix: Vec<u8>) -> Result<Self> {
+        if prefix.is_empty() {
+            return Err(eg!("the namespace prefix can not be empty"));
+        }
+        let in_disk =
+            backend::Mapx::load_or_create_in_namespace(path, &prefix).c(d!())?;
+        Ok(Mapx { in_disk })
+    }
+
+    /// Create an instance with a full merge operator,
+    /// which will be used by `merge(...)`.
+    ///
+    /// Both the existing value and the operands are JSON-encoded `V`,
+    /// and so must be the merged value; the key is the bincode-encoded `K`.
+    ///
+    /// The name is saved with the instance, it must be the same when reopening.
+    #[cfg(feature = "merge-operator")]
+    pub fn with_merge_operator<F>(path: &str, name: &str, f: F) -> Result<Self>
+    where
+        F: Fn(&[u8], Option<&[u8]>, &mut rocksdb::MergeOperands) -> Option<Vec<u8>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let db = Self::new(path).c(d!())?;
+        db.in_disk
+            .set_merge_operator(name, Box::new(f))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Merge a partial value into the value of `key`
+    /// with the merge operator set by `with_merge_operator(...)`,
+    /// without a read-modify-write cycle.
+    ///
+    /// An error will be returned if no merge operator has been set
+    /// for this instance in the current process.
+    #[cfg(feature = "merge-operator")]
+    #[inline(always)]
+    pub fn merge(&mut self, key: K, partial_value: V) -> Result<()> {
+        self.in_disk.merge(key, partial_value).c(d!())
+    }
+
+    /// Create an instance whose operations will be counted,
+    /// see `metrics()`.
+    ///
+    /// The counters live in memory, and are shared by the clones of the instance.
+    #[inline(always)]
+    pub fn with_metrics(path: &str) -> Result<Self> {
+        let mut db = Self::new(path).c(d!())?;
+        db.in_disk.enable_metrics();
+        Ok(db)
+    }
+
+    /// Get the operation counters,
+    /// `None` will be returned if the instance is not created by `with_metrics()`.
+    #[inline(always)]
+    pub fn metrics(&self) -> Option<&MapxMetrics> {
+        self.in_disk.metrics()
+    }
+
+    /// Create an empty instance at a unique path with the same options as this one,
+    /// see `clone_empty_at()`.
+    #[inline(always)]
+    pub fn clone_empty(&self) -> Result<Self> {
+        self.clone_empty_at(&crate::unique_path!()).c(d!())
+    }
+
+    /// Create an empty instance at `new_path` with the same options as this one,
+    /// the data is not copied.
+    ///
+    /// Only the metrics option is carried for now(with new counters),
+    /// the storage options are shared by all the collections of the db instance.
+    ///
+    /// An error will be returned if a non-empty instance exists at `new_path`.
+    pub fn clone_empty_at(&self, new_path: &str) -> Result<Self> {
+        let mut db = Self::new(new_path).c(d!())?;
+        if !db.is_empty() {
+            return Err(eg!(format!("'{}' is not empty", new_path)));
+        }
+        if self.metrics().is_some() {
+            db.in_disk.enable_metrics();
+        }
+        Ok(db)
+    }
+
+    /// Move the keys which have not been accessed(read or written)
+    /// in the last `access_threshold` writes to `policy.cold_store`,
+    /// the eviction is checked on every write, including the batch ones.
+    ///
+    /// `get`, `contains_key`, `insert`, `remove` and `move_entry` check both stores
+    /// transparently, a cold key is moved back to the primary store when it is
+    /// written again; `subtract_keys`, `apply_patch` and `atomic_swap_all` remove
+    /// the cold keys too.
+    ///
+    /// **NOTE**:
+    /// - the access records of the primary keys are kept in memory
+    /// - other methods, such as `len` and `iter`, only see the primary store
+    #[inline(always)]
+    pub fn set_eviction_policy(&mut self, policy: EvictionPolicy<K, V>) {
+        self.in_disk
+            .set_eviction(policy.cold_store.in_disk, policy.access_threshold);
+    }
+
+    /// Create an instance whose writes will also be appended to
+    /// a NDJSON file at `log_path`, one line per written entry, eg:
+    /// `{"op":"insert","key":...,"value":...,"ts":...}`,
+    /// and `atomic_swap_all` logs a `{"op":"clear","ts":...}` before the new entries.
+    ///
+    /// The log is buffered, and will be flushed by `flush()` or on drop.
+    ///
+    /// **NOTE**: `insert_raw_bytes` and `WriteBatchBuilder` are not logged.
+    #[inline(always)]
+    pub fn with_change_log(path: &str, log_path: &str) -> Result<Self> {
+        let mut db = Self::new(path).c(d!())?;
+        db.in_disk.set_change_log(log_path).c(d!())?;
+        Ok(db)
+    }
+
+    /// Reconstruct an instance at `dest_path` from a change log.
+    pub fn replay_change_log(log_path: &Path, dest_path: &str) -> Result<Self> {
+        let mut db = Self::new(dest_path).c(d!())?;
+
+        let f = File::open(log_path).c(d!())?;
+        for line in BufReader::new(f).lines() {
+            let line = line.c(d!())?;
+            if line.is_empty() {
+                continue;
+            }
+            let entry: ChangeLogEntry<K, V> = serde_json::from_str(&line).c(d!())?;
+            match (entry.op, entry.key, entry.value) {
+                (ChangeOp::Insert, Some(k), Some(v)) => db.set_value(k, v),
+                (ChangeOp::Remove, Some(k), _) => db.unset_value(&k),
+                (ChangeOp::Clear, _, _) => db.in_disk.replace_all(vec![]).c(d!())?,
+                _ => return Err(eg!("missing key or value")),
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// Flush the data and the change log(if any) to disk.
+    #[inline(always)]
+    pub fn flush(&self) -> Result<()> {
+        self.in_disk.flush().c(d!())
+    }
+
     /// Get the database storage path
     pub fn get_path(&self) -> &str {
         self.in_disk.get_path()
     }
 
+    /// Subscribe the write events of this instance,
+    /// every written entry will publish an event,
+    /// the batch operations publish their events after the batch has been written.
+    ///
+    /// **NOTE**: `insert_raw_bytes` and `WriteBatchBuilder` do not publish any event.
+    #[cfg(feature = "async")]
+    #[inline(always)]
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<MapxEvent<K, V>> {
+        self.in_disk.subscribe()
+    }
+
+    /// Register a callback which will be called synchronously
+    /// after every written entry, in the order of registration.
+    ///
+    /// **NOTE**: the same as `subscribe()`,
+    /// `insert_raw_bytes` and `WriteBatchBuilder` are not observed.
+    #[cfg(feature = "observe")]
+    #[inline(always)]
+    pub fn register_observer<F>(&mut self, observer: F)
+    where
+        F: Fn(MapxChangeEvent<K, V>) + Send + Sync + 'static,
+    {
+        self.in_disk.register_observer(Arc::new(observer));
+    }
+
+    /// Remove all the observers registered by `register_observer()`.
+    #[cfg(feature = "observe")]
+    #[inline(always)]
+    pub fn remove_all_observers(&mut self) {
+        self.in_disk.remove_all_observers();
+    }
+
+    /// Move the instance to a new path,
+    /// then it can only be opened at the new path.
+    ///
+    /// **NOTE**:
+    /// - an error will be returned if anything exists at the new path,
+    ///   so it can NOT be used to replace an existing instance
+    /// - if the new path is hashed to another db instance, all the entries are
+    ///   copied in one batch(held in memory) and then deleted in another one,
+    ///   the two writes are NOT atomic as a whole
+    #[inline(always)]
+    pub fn rename(&mut self, new_path: &str) -> Result<()> {
+        self.in_disk.rename(new_path).c(d!())
+    }
+
     /// Imitate the behavior of 'BTreeMap<_>.get(...)'
     ///
     /// Any faster/better choice other than JSON ?
     #[inline(always)]
     pub fn get(&self, key: &K) -> Option<V> {
-        self.in_disk.get(key)
+        let v = self.in_disk.get(key);
+        if let Some(m) = self.metrics() {
+            if v.is_some() {
+                MapxMetrics::incr(&m.get_hit_count);
+            } else {
+                MapxMetrics::incr(&m.get_miss_count);
+            }
+        }
+        v
+    }
+
+    /// Read the raw value of a bincode-serialized key,
+    /// neither the key nor the value is decoded.
+    ///
+    /// **NOTE**: the entries moved to the cold store are not visible to this.
+    #[inline(always)]
+    pub fn get_raw_bytes(&self, serialized_key: &[u8]) -> Option<Vec<u8>> {
+        self.in_disk.get_raw_bytes(serialized_key)
+    }
+
+    /// Write a raw entry, bypassing the serialization of `K` and `V`,
+    /// the key must be serialized by bincode and the value by serde_json.
+    ///
+    /// **NOTE**: the entry is written as is, any malformed one will corrupt the map,
+    /// and the typed reads of it will panic.
+    /// The change log, the events and the metrics are not updated.
+    #[inline(always)]
+    pub fn insert_raw_bytes(
+        &mut self,
+        serialized_key: Vec<u8>,
+        serialized_value: Vec<u8>,
+    ) {
+        self.in_disk
+            .insert_raw_bytes(serialized_key, serialized_value)
+    }
+
+    /// Same as `get`, but `f` is called with the result before returning it,
+    /// useful for logging without changing the return types.
+    #[inline(always)]
+    pub fn tap_get<F>(&self, key: &K, f: F) -> Option<V>
+    where
+        F: FnOnce(Option<&V>),
+    {
+        let v = self.get(key);
+        f(v.as_ref());
+        v
+    }
+
+    /// Same as `iter`, but `f` is called with each entry before yielding it.
+    #[inline(always)]
+    pub fn tap_iter<F>(&self, f: F) -> TappedMapxIter<'_, K, V, F>
+    where
+        F: FnMut(&K, &V),
+    {
+        TappedMapxIter {
+            iter: self.iter_exact(),
+            f,
+        }
+    }
+
+    /// Load the given keys into the block cache in a background thread,
+    /// this returns immediately, and the reads are not affected.
+    #[inline(always)]
+    pub fn warm_up(&self, keys: &[K]) {
+        self.in_disk.warm_up(keys);
+    }
+
+    /// Load the keys in `range` into the block cache in a background thread,
+    /// this returns immediately, and the reads are not affected.
+    ///
+    /// All the keys are scanned, see the storage notes of this module.
+    #[inline(always)]
+    pub fn warm_up_range<R>(&self, range: R)
+    where
+        K: Send + 'static,
+        R: RangeBounds<K> + Send + 'static,
+    {
+        self.in_disk.warm_up_range(range);
+    }
+
+    /// Get the values of three keys from one snapshot,
+    /// so they are consistent even if there are concurrent writes.
+    ///
+    /// This is only a helper for the common three-key case,
+    /// use `get` for the others.
+    pub fn get_three(
+        &self,
+        k1: &K,
+        k2: &K,
+        k3: &K,
+    ) -> (Option<V>, Option<V>, Option<V>) {
+        let mut res = self.in_disk.get_consistent(&[k1, k2, k3]).into_iter();
+        (
+            res.next().flatten(),
+            res.next().flatten(),
+            res.next().flatten(),
+        )
+    }
+
+    /// Get the value with a rough time when it was written,
+    /// which is the modification time of the newest SST file
+    /// whose key range covers the key.
+    ///
+    /// **NOTE**:
+    /// - the current time is returned if the key has not been flushed to any SST file
+    /// - the time may be earlier than the real one
+    ///   if the key has been updated after the last flush,
+    ///   or later if the file has been rewritten by a compaction
+    pub fn get_with_timestamp_hint(&self, key: &K) -> Option<(V, SystemTime)> {
+        self.get(key)
+            .map(|v| (v, self.in_disk.write_time_hint(key)))
+    }
+
+    /// Get all the versions of the value of `key`, the oldest one first.
+    ///
+    /// **NOTE**: no history is kept for now, so only the current version will be
+    /// returned, its timestamp is the same as `get_with_timestamp_hint()`.
+    pub fn get_all_versions(&self, key: &K) -> Vec<VersionedEntry<V>> {
+        self.get_with_timestamp_hint(key)
+            .map(|(value, timestamp)| VersionedEntry { value, timestamp })
+            .into_iter()
+            .collect()
     }
 
     /// Imitate the behavior of 'BTreeMap<_>.get_mut(...)'
@@ -90,53 +740,1211 @@ where
         self.in_disk.len()
     }
 
+    /// Count the keys in `range` by scanning the whole collection,
+    /// this is O(N), but always precise, see the storage notes of this module.
+    pub fn count_keys_in_range_exact<R: RangeBounds<K>>(&self, range: R) -> usize {
+        self.iter().filter(|(k, _)| range.contains(k)).count()
+    }
+
+    /// Get the disk usage of the underlying SST files of the whole db instance,
+    /// see the storage notes of this module.
+    #[inline(always)]
+    pub fn approximate_size_bytes(&self) -> u64 {
+        self.in_disk.approximate_size_bytes()
+    }
+
+    /// Get the memory usage of the block cache and the memtables
+    /// of the whole db instance, see the storage notes of this module.
+    #[inline(always)]
+    pub fn approximate_memory_bytes(&self) -> u64 {
+        self.in_disk.approximate_memory_bytes()
+    }
+
+    /// Get the average size of the entries of the whole db instance
+    /// from the metadata of rocksdb, `None` if it can not be estimated,
+    /// see the storage notes of this module.
+    #[inline(always)]
+    pub fn average_entry_size_bytes(&self) -> Option<u64> {
+        self.in_disk.average_entry_size_bytes()
+    }
+
+    /// Tune the memtables before writing about `estimated_entries` entries,
+    /// the `write_buffer_size` is set to the estimated size of the entries,
+    /// in the range of `[64MB, 256MB]`, with at most 4 memtables.
+    ///
+    /// The options are kept until `reset_capacity_hint` is called.
+    ///
+    /// **NOTE**: the options belong to the underlying db instance,
+    /// so all the collections in it are affected, and the latest hint wins.
+    #[inline(always)]
+    pub fn capacity_hint(&mut self, estimated_entries: usize) -> Result<()> {
+        self.in_disk
+            .capacity_hint(estimated_entries)
+            .c(d!())
+            .map(|_| ())
+    }
+
+    /// Restore the default memtable options changed by `capacity_hint`.
+    #[inline(always)]
+    pub fn reset_capacity_hint(&mut self) -> Result<()> {
+        self.in_disk.reset_capacity_hint().c(d!())
+    }
+
+    /// Get the memory usage of the whole db instance by components,
+    /// for capacity planning,
+    /// see `MemoryBreakdown` and the storage notes of this module.
+    #[inline(always)]
+    pub fn estimate_memory_overhead(&self) -> MemoryBreakdown {
+        pnk!(self.in_disk.memory_breakdown())
+    }
+
+    /// Get `num_deletions / (num_entries + num_deletions)` of the whole db instance,
+    /// `num_entries` is the number of the live entries, `0.0` means no fragmentation,
+    /// the max value is `1.0`, see the storage notes of this module.
+    #[inline(always)]
+    pub fn fragmentation_ratio(&self) -> f64 {
+        self.in_disk.fragmentation_ratio()
+    }
+
+    /// The opposite of `fragmentation_ratio()`.
+    #[inline(always)]
+    pub fn live_entry_ratio(&self) -> f64 {
+        1.0 - self.fragmentation_ratio()
+    }
+
+    /// Suggest a compaction if `fragmentation_ratio()` is greater than `0.5`.
+    #[inline(always)]
+    pub fn should_compact(&self) -> bool {
+        self.fragmentation_ratio() > 0.5
+    }
+
+    /// Check if the length counter matches the real number of entries,
+    /// and if all the keys and values can be decoded.
+    ///
+    /// All the entries will be scanned, so it is slow on large collections.
+    #[inline(always)]
+    pub fn validate_invariants(&self) -> ValidationReport {
+        self.in_disk.validate()
+    }
+
+    /// Trigger a full compaction of the whole db instance to clean up
+    /// the deleted entries, see the storage notes of this module.
+    #[inline(always)]
+    pub fn compact_all(&self) -> Result<()> {
+        self.in_disk.compact_all();
+        Ok(())
+    }
+
+    /// Similar with `compact_all`, but return an error if it can not be done
+    /// within `dur`, the compaction will continue in the background in that case.
+    #[inline(always)]
+    pub fn compact_with_timeout(&self, dur: Duration) -> Result<()> {
+        self.in_disk.compact_with_timeout(dur).c(d!())
+    }
+
     /// A helper func
     #[inline(always)]
     pub fn is_empty(&self) -> bool {
         self.in_disk.is_empty()
     }
 
-    /// Imitate the behavior of 'BTreeMap<_>.insert(...)'.
-    #[inline(always)]
-    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
-        self.in_disk.insert(key, value)
+    /// Imitate the behavior of 'BTreeMap<_>.insert(...)'.
+    ///
+    /// The old value will be returned if the key exists.
+    #[inline(always)]
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(m) = self.metrics() {
+            MapxMetrics::incr(&m.insert_count);
+        }
+        self.in_disk.insert(key, value)
+    }
+
+    // Used by [WriteBatchBuilder](crate::batch::WriteBatchBuilder).
+    #[inline(always)]
+    pub(crate) fn batch_set_value(
+        &self,
+        batches: &mut [WriteBatch],
+        staged: &mut HashSet<(usize, Vec<u8>)>,
+        key: K,
+        value: V,
+    ) -> Result<bool> {
+        self.in_disk
+            .batch_set_value(batches, staged, key, value)
+            .c(d!())
+    }
+
+    // Used by [WriteBatchBuilder](crate::batch::WriteBatchBuilder).
+    #[inline(always)]
+    pub(crate) fn counter(&self) -> ((usize, Vec<u8>), Arc<AtomicUsize>) {
+        self.in_disk.counter()
+    }
+
+    /// Imitate the behavior of 'mem::replace(...)',
+    /// set a new value and return the old one.
+    #[inline(always)]
+    pub fn swap_value(&mut self, key: &K, new_value: V) -> Option<V> {
+        self.insert(key.clone(), new_value)
+    }
+
+    /// Similar with `insert`, but ignore the old value.
+    #[inline(always)]
+    pub fn set_value(&mut self, key: K, value: V) {
+        if let Some(m) = self.metrics() {
+            MapxMetrics::incr(&m.insert_count);
+        }
+        self.in_disk.set_value(key, value);
+    }
+
+    /// Imitate the behavior of 'BTreeMap<_>.try_insert(...)',
+    /// the existing value will never be overwritten,
+    /// and the given <K, V> will be returned back in the error.
+    #[inline(always)]
+    pub fn try_insert(&mut self, key: K, value: V) -> std::result::Result<(), (K, V)> {
+        if self.contains_key(&key) {
+            Err((key, value))
+        } else {
+            self.set_value(key, value);
+            Ok(())
+        }
+    }
+
+    /// Insert the <K, V> only if the key does not exist,
+    /// return `true` if the insertion has happened.
+    #[inline(always)]
+    pub fn put_if_absent(&mut self, key: K, value: V) -> bool {
+        self.try_insert(key, value).is_ok()
+    }
+
+    /// Overwrite the value only if the key exists,
+    /// return the old value or `None`(nothing will be inserted).
+    #[inline(always)]
+    pub fn replace_if_present(&mut self, key: &K, value: V) -> Option<V> {
+        if self.contains_key(key) {
+            self.insert(key.clone(), value)
+        } else {
+            None
+        }
+    }
+
+    /// Get the entry with the smallest key(by the `Ord` of `K`) without removing it,
+    /// all the keys are scanned, see the storage notes of this module.
+    #[inline(always)]
+    pub fn peek_first(&self) -> Option<(K, V)> {
+        self.in_disk.first()
+    }
+
+    /// Get the entry with the largest key(by the `Ord` of `K`) without removing it,
+    /// all the keys are scanned, see the storage notes of this module.
+    #[inline(always)]
+    pub fn peek_last(&self) -> Option<(K, V)> {
+        self.in_disk.last()
+    }
+
+    /// Imitate the behavior of '.entry(...).or_insert(...)'
+    #[inline(always)]
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        Entry { key, db: self }
+    }
+
+    /// Get a mutable handle of the value of `key`,
+    /// `default` will be inserted if the key does not exist.
+    ///
+    /// A shortcut of `.entry(key).or_insert(default)`.
+    #[inline(always)]
+    pub fn get_with_default(&mut self, key: K, default: V) -> ValueMut<'_, K, V> {
+        self.entry(key).or_insert(default)
+    }
+
+    /// Imitate the behavior of '.iter()'
+    #[inline(always)]
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        Box::new(self.iter_exact())
+    }
+
+    /// Same as `iter()`, but the concrete iterator is returned,
+    /// which implements `ExactSizeIterator` and `FusedIterator`.
+    #[inline(always)]
+    pub fn iter_exact(&self) -> MapxIter<'_, K, V> {
+        if let Some(m) = self.metrics() {
+            MapxMetrics::incr(&m.iter_count);
+        }
+        MapxIter {
+            iter: self.in_disk.iter(),
+            len: self.len(),
+        }
+    }
+
+    /// Same as '.iter().skip(n)', but the skipped entries will not be deserialized.
+    ///
+    /// **NOTE**: rocksdb can not seek to an offset,
+    /// so the skipped entries are still read from the storage.
+    #[inline(always)]
+    pub fn iter_skip(&self, n: usize) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        Box::new(self.in_disk.iter_skip(n))
+    }
+
+    /// Get the key at the position `n`(starting from 0) in the storage(encoded byte)
+    /// order, the same as `iter()`, the other entries will not be deserialized.
+    ///
+    /// **NOTE**: the position may differ from the rank by the `Ord` of `K`,
+    /// use `pick_by_rank()` for the latter.
+    #[inline(always)]
+    pub fn nth_key(&self, n: usize) -> Option<K> {
+        self.in_disk.nth_key(n)
+    }
+
+    /// Convert into an iterator which owns the instance,
+    /// the entries are read lazily one by one, and nothing is buffered.
+    ///
+    /// Unlike `iter()`, a decoding error is returned instead of panicking.
+    #[inline(always)]
+    pub fn into_streaming_iter(self) -> StreamingMapxIter<K, V> {
+        let (iter, prefix_len) = self.in_disk.raw_iter();
+        StreamingMapxIter {
+            iter,
+            prefix_len,
+            _hdr: self,
+        }
+    }
+
+    /// Iterate over the entries whose bincode-serialized key starts with `prefix`,
+    /// the keys out of the range will not be deserialized.
+    #[inline(always)]
+    pub fn scan_prefix_bytes(
+        &self,
+        prefix: &[u8],
+    ) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        Box::new(self.in_disk.scan_prefix_bytes(prefix))
+    }
+
+    /// Count the entries whose bincode-serialized key starts with `serialized_prefix`,
+    /// nothing will be deserialized.
+    ///
+    /// **NOTE**: the result is an estimate,
+    /// entries written during the counting may or may not be included.
+    #[inline(always)]
+    pub fn count_prefix_hits(&self, serialized_prefix: &[u8]) -> Result<usize> {
+        Ok(self.in_disk.count_prefix_hits(serialized_prefix))
+    }
+
+    /// Read a page of at most `limit` entries after `start`(exclusive)
+    /// in the storage(encoded byte) order, `None` means reading from the first entry.
+    ///
+    /// The returned cursor is the `start` of the next page, `None` means the end
+    /// has been reached; the next page is resumed from the encoded bytes of it,
+    /// so the pages are NOT sorted by the `Ord` of `K`.
+    pub fn scan_with_limit(
+        &self,
+        start: Option<&K>,
+        limit: usize,
+    ) -> (Vec<(K, V)>, Option<K>) {
+        let page = self.in_disk.scan_with_limit(start, limit);
+        let cursor = alt!(
+            0 < limit && page.len() == limit,
+            page.last().map(|(k, _)| k.clone()),
+            None
+        );
+        (page, cursor)
+    }
+
+    /// Read the entries in batches of at most `batch_size` entries
+    /// in the storage(encoded byte) order, see `scan_with_limit()`,
+    /// each batch is read synchronously when it is polled,
+    /// so no iterator is held across the `.await` points of the caller.
+    #[cfg(feature = "async")]
+    pub fn iter_in_batches_async(
+        &self,
+        batch_size: usize,
+    ) -> impl futures::Stream<Item = Vec<(K, V)>> + '_ {
+        // the state is the `start` of the next batch, `None` means the end
+        futures::stream::unfold(Some(None), move |cursor: Option<Option<K>>| {
+            let page =
+                cursor.map(|start| self.scan_with_limit(start.as_ref(), batch_size));
+            async move {
+                let (batch, next) = page?;
+                alt!(batch.is_empty(), None, Some((batch, next.map(Some))))
+            }
+        })
+    }
+
+    /// Iterate with the 0-indexed rank of each entry, sorted by the `Ord` of `K`.
+    ///
+    /// **NOTE**: all the entries are loaded into memory, see `to_sorted_vec()`.
+    #[inline(always)]
+    pub fn iter_enumerated(&self) -> impl Iterator<Item = (usize, K, V)> {
+        self.to_sorted_vec()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (k, v))| (i, k, v))
+    }
+
+    /// Call `f` on every entry with `parallelism` threads,
+    /// and return after all the calls are finished.
+    ///
+    /// All the entries are read into memory before the calls,
+    /// and the order of the calls is not specified.
+    ///
+    /// This will panic if `parallelism` is 0, or if `f` panics.
+    pub fn for_each_concurrent<F>(&self, parallelism: usize, f: F)
+    where
+        K: Send + 'static,
+        V: Send + 'static,
+        F: Fn(K, V) + Send + Sync + 'static,
+    {
+        assert!(0 < parallelism, "parallelism must be non-zero");
+
+        let entries = Arc::new(Mutex::new(self.iter().collect::<Vec<_>>().into_iter()));
+        let f = Arc::new(f);
+
+        let workers = (0..parallelism)
+            .map(|_| {
+                let entries = Arc::clone(&entries);
+                let f = Arc::clone(&f);
+                thread::spawn(move || loop {
+                    let next = pnk!(entries.lock().map_err(|e| eg!(e))).next();
+                    match next {
+                        Some((k, v)) => f(k, v),
+                        None => break,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for w in workers {
+            if let Err(e) = w.join() {
+                panic::resume_unwind(e);
+            }
+        }
+    }
+
+    /// Iterate in chunks of `batch_size` entries in the storage(encoded byte) order,
+    /// the last chunk may be smaller.
+    ///
+    /// Like 'slice::chunks(...)', this will panic if `batch_size` is 0.
+    pub fn iter_batched(
+        &self,
+        batch_size: usize,
+    ) -> impl Iterator<Item = Vec<(K, V)>> + '_ {
+        assert!(0 < batch_size, "batch_size must be non-zero");
+
+        let mut iter = self.iter();
+        std::iter::from_fn(move || {
+            let batch = iter.by_ref().take(batch_size).collect::<Vec<_>>();
+            alt!(batch.is_empty(), None, Some(batch))
+        })
+    }
+
+    /// Count the entries which satisfy the predicate.
+    #[inline(always)]
+    pub fn count_matching<F>(&self, mut pred: F) -> usize
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.iter().filter(|(k, v)| pred(k, v)).count()
+    }
+
+    /// Sum the results of `value_fn` over the entries
+    /// which satisfy the predicate.
+    #[inline(always)]
+    pub fn sum_matching<F, G, S>(&self, mut pred: F, value_fn: G) -> S
+    where
+        F: FnMut(&K, &V) -> bool,
+        G: Fn(&V) -> S,
+        S: Sum,
+    {
+        self.iter()
+            .filter(|(k, v)| pred(k, v))
+            .map(|(_, v)| value_fn(&v))
+            .sum()
+    }
+
+    /// Imitate the behavior of '.iter().fold(...)',
+    /// the entries are visited in the storage(encoded byte) order, the same as `iter()`.
+    #[inline(always)]
+    pub fn reduce<B, F>(&self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, K, V) -> B,
+    {
+        self.iter().fold(init, |acc, (k, v)| f(acc, k, v))
+    }
+
+    /// Same as `reduce`, but only the values are visited.
+    #[inline(always)]
+    pub fn reduce_values<B, F>(&self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, V) -> B,
+    {
+        self.iter().fold(init, |acc, (_, v)| f(acc, v))
+    }
+
+    /// Same as `reduce`, but only the keys are visited,
+    /// the values will not be deserialized.
+    #[inline(always)]
+    pub fn reduce_keys<B, F>(&self, init: B, f: F) -> B
+    where
+        F: FnMut(B, K) -> B,
+    {
+        self.in_disk.iter_keys().fold(init, f)
+    }
+
+    /// Make this instance the same as `source`,
+    /// all the changes are written in one batch.
+    ///
+    /// The number of changed(inserted, updated or removed) entries will be returned,
+    /// so `0` will be returned if they are already the same.
+    pub fn sync_from(&mut self, source: &Mapx<K, V>) -> Result<usize> {
+        let mut patch = source
+            .iter()
+            .filter(|(k, v)| self.in_disk.get(k).as_ref() != Some(v))
+            .map(|(k, v)| PatchEntry::Insert(k, v))
+            .collect::<Vec<_>>();
+        patch.extend(
+            self.in_disk
+                .iter_keys()
+                .filter(|k| !source.in_disk.contains_key(k))
+                .map(PatchEntry::Remove),
+        );
+
+        let n = patch.len();
+        self.apply_patch(patch).c(d!()).map(|_| n)
+    }
+
+    /// Run `sync_from` in a background thread every `interval`,
+    /// until the returned handle is stopped or dropped.
+    ///
+    /// The errors of the background thread are printed, and the next round continues.
+    pub fn sync_continuously(
+        dest: Arc<RwLock<Mapx<K, V>>>,
+        source: Arc<RwLock<Mapx<K, V>>>,
+        interval: Duration,
+    ) -> SyncHandle
+    where
+        K: Send + Sync + 'static,
+        V: Send + Sync + 'static,
+    {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stopped);
+
+        let worker = thread::spawn(move || {
+            while !flag.load(AtomicOrdering::Relaxed) {
+                let res = source.read().map_err(|e| eg!(e)).and_then(|src| {
+                    dest.write()
+                        .map_err(|e| eg!(e))
+                        .and_then(|mut dst| dst.sync_from(&src).c(d!()))
+                });
+                if let Err(e) = res {
+                    e.print(None);
+                }
+                thread::park_timeout(interval);
+            }
+        });
+
+        SyncHandle {
+            stopped,
+            worker: Some(worker),
+        }
+    }
+
+    /// Apply all the operations of `patch` atomically in one batch,
+    /// the operations on the same key take effect in the given order.
+    #[inline(always)]
+    pub fn apply_patch(&mut self, patch: Vec<PatchEntry<K, V>>) -> Result<()> {
+        self.in_disk.apply_patch(patch).c(d!())
+    }
+
+    /// Replace all the entries with `new_entries` atomically,
+    /// after that, only the new entries exist.
+    ///
+    /// A `Cleared` event is published before the events of the new entries.
+    #[inline(always)]
+    pub fn atomic_swap_all<I>(&mut self, new_entries: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.in_disk.replace_all(new_entries).c(d!())
+    }
+
+    /// Update all the entries in one batch,
+    /// the entry will be removed if `f` returns `None`.
+    #[inline(always)]
+    pub fn update_batch<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnMut(&K, V) -> Option<V>,
+    {
+        self.in_disk.update_batch(f).c(d!())
+    }
+
+    /// Imitate the behavior of 'BTreeMap<_>.retain(...)', but only the keys are checked,
+    /// so the values will not be deserialized.
+    ///
+    /// All the removals are written in one batch.
+    #[inline(always)]
+    pub fn retain_keys<F>(&mut self, f: F)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        pnk!(self.in_disk.retain_keys(f))
+    }
+
+    /// Imitate the behavior of 'BTreeMap<_>.retain(...)', but only the values are checked,
+    /// so the keys will not be deserialized.
+    ///
+    /// All the removals are written in one batch.
+    #[inline(always)]
+    pub fn retain_values<F>(&mut self, f: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        pnk!(self.in_disk.retain_values(f))
+    }
+
+    /// Insert all the entries of `other` in one batch,
+    /// `resolve(key, self_value, other_value)` decides the value
+    /// if a key exists in both instances.
+    pub fn union_with<F>(&mut self, other: &Mapx<K, V>, mut resolve: F)
+    where
+        F: FnMut(&K, &V, &V) -> V,
+    {
+        let entries = other
+            .iter()
+            .map(|(k, v)| match self.in_disk.get(&k) {
+                Some(sv) => {
+                    let v = resolve(&k, &sv, &v);
+                    (k, v)
+                }
+                None => (k, v),
+            })
+            .collect::<Vec<_>>();
+        pnk!(self.in_disk.set_value_batch(entries));
+    }
+
+    /// Remove all the keys which exist in `other` in one batch,
+    /// the values of `other` are ignored.
+    ///
+    /// The number of removed entries will be returned.
+    pub fn subtract_keys<V2>(&mut self, other: &Mapx<K, V2>) -> usize
+    where
+        V2: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+    {
+        pnk!(self.in_disk.remove_batch(other.iter().map(|(k, _)| k)))
+    }
+
+    /// Create a new instance at `dest_path`,
+    /// which contains the entries satisfying the predicate.
+    pub fn filter_collect<F>(&self, mut pred: F, dest_path: &str) -> Result<Mapx<K, V>>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut db = Mapx::new(dest_path).c(d!())?;
+        db.in_disk
+            .set_value_batch(self.iter().filter(|(k, v)| pred(k, v)))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Create a new instance at `dest_path`,
+    /// which contains the transformed entries.
+    pub fn map_collect<F, V2>(&self, mut f: F, dest_path: &str) -> Result<Mapx<K, V2>>
+    where
+        F: FnMut(K, V) -> (K, V2),
+        V2: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+    {
+        let mut db = Mapx::new(dest_path).c(d!())?;
+        db.in_disk
+            .set_value_batch(self.iter().map(|(k, v)| f(k, v)))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Create a new instance at `dest_path`,
+    /// in which the values are keys and the keys are values.
+    ///
+    /// If multiple keys have the same value,
+    /// only the last one in storage(encoded byte) order will be kept.
+    pub fn invert(&self, dest_path: &str) -> Result<Mapx<V, K>>
+    where
+        V: Eq + PartialOrd + Ord + Hash,
+    {
+        let mut db = Mapx::new(dest_path).c(d!())?;
+        db.in_disk
+            .set_value_batch(self.iter().map(|(k, v)| (v, k)))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Create a new instance at `dest_path`, in which the keys are mapped by `f`,
+    /// and the values are the same.
+    ///
+    /// If multiple keys are mapped to the same new key,
+    /// only the last one in the storage(encoded byte) order will be kept.
+    pub fn transform_keys<K2, F>(&self, dest_path: &str, f: F) -> Result<Mapx<K2, V>>
+    where
+        K2: Clone
+            + PartialEq
+            + Eq
+            + PartialOrd
+            + Ord
+            + Hash
+            + Serialize
+            + DeserializeOwned
+            + fmt::Debug,
+        F: Fn(K) -> K2,
+    {
+        let mut db = Mapx::new(dest_path).c(d!())?;
+        db.in_disk
+            .set_value_batch(self.iter().map(|(k, v)| (f(k), v)))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Create a new instance at `dest_path`, in which the values are mapped by `f`,
+    /// and the keys are the same, the value type can be changed.
+    pub fn transform_values<V2, F>(&self, dest_path: &str, f: F) -> Result<Mapx<K, V2>>
+    where
+        V2: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+        F: Fn(&K, V) -> V2,
+    {
+        let mut db = Mapx::new(dest_path).c(d!())?;
+        db.in_disk
+            .set_value_batch(self.iter().map(|(k, v)| {
+                let v = f(&k, v);
+                (k, v)
+            }))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// The in-place variant of `transform_values`,
+    /// all the new values are written in one batch.
+    #[inline(always)]
+    pub fn transform_values_in_place<F>(&mut self, f: F) -> Result<()>
+    where
+        F: Fn(&K, V) -> V,
+    {
+        self.in_disk.update_batch(|k, v| Some(f(k, v))).c(d!())
+    }
+
+    /// Create a new instance at `dest_path` with all the entries produced by `f`,
+    /// `f` is applied to every entry in storage(encoded byte) order.
+    ///
+    /// If multiple entries have the same key, the last one wins.
+    pub fn flat_map<K2, V2, I, F>(&self, dest_path: &str, f: F) -> Result<Mapx<K2, V2>>
+    where
+        K2: Clone
+            + PartialEq
+            + Eq
+            + PartialOrd
+            + Ord
+            + Hash
+            + Serialize
+            + DeserializeOwned
+            + fmt::Debug,
+        V2: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+        I: IntoIterator<Item = (K2, V2)>,
+        F: Fn(K, V) -> I,
+    {
+        let mut db = Mapx::new(dest_path).c(d!())?;
+        db.in_disk
+            .set_value_batch(self.iter().flat_map(|(k, v)| f(k, v)))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Same as `invert`, create a new instance at `dest_path`,
+    /// in which the values are keys and the keys are values.
+    ///
+    /// If multiple keys have the same value(the map is not injective),
+    /// the last one in the storage(encoded byte) order wins.
+    #[inline(always)]
+    pub fn flip_key_value(&self, dest_path: &str) -> Result<Mapx<V, K>>
+    where
+        V: Eq + PartialOrd + Ord + Hash,
+    {
+        self.invert(dest_path).c(d!())
+    }
+
+    /// The in-place variant of `flip_key_value`,
+    /// the flipped entries replace the original ones at the same path atomically.
+    ///
+    /// If multiple keys have the same value(the map is not injective),
+    /// the last one in the storage(encoded byte) order wins.
+    pub fn flip_key_value_in_place(self) -> Result<Mapx<V, K>>
+    where
+        V: Eq + PartialOrd + Ord + Hash,
+    {
+        let mut db = Mapx::new(self.get_path()).c(d!())?;
+        db.in_disk
+            .replace_all(self.iter().map(|(k, v)| (v, k)))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Create a new instance at `dest_path`, in which every run of
+    /// consecutive(in key order) equal values is merged into one entry,
+    /// the key is the first key of the run,
+    /// and the value is `(value, length of the run)`.
+    ///
+    /// **NOTE**: all the entries are loaded into memory, see `to_sorted_vec()`.
+    pub fn run_length_encode(&self, dest_path: &str) -> Result<Mapx<K, (V, usize)>> {
+        let mut runs: Vec<(K, (V, usize))> = vec![];
+        for (k, v) in self.to_sorted_vec() {
+            match runs.last_mut() {
+                Some((_, (last, n))) if *last == v => *n += 1,
+                _ => runs.push((k, (v, 1))),
+            }
+        }
+
+        let mut db = Mapx::new(dest_path).c(d!())?;
+        db.in_disk.set_value_batch(runs).c(d!()).map(|_| db)
+    }
+
+    /// Create a new instance at `path`, which maps `key_fn(value)` to the key,
+    /// so the entries can be looked up by a secondary key.
+    ///
+    /// **NOTE**:
+    /// - the index is a snapshot, it will not be updated with this instance
+    /// - if multiple values have the same secondary key,
+    ///   only the last one in the storage(encoded byte) order will be kept
+    pub fn build_secondary_index<F, S>(
+        &self,
+        path: &str,
+        key_fn: F,
+    ) -> Result<Mapx<S, K>>
+    where
+        F: Fn(&V) -> S,
+        S: Clone
+            + PartialEq
+            + Eq
+            + PartialOrd
+            + Ord
+            + Hash
+            + Serialize
+            + DeserializeOwned
+            + fmt::Debug,
+    {
+        let mut db = Mapx::new(path).c(d!())?;
+        db.in_disk
+            .set_value_batch(self.iter().map(|(k, v)| (key_fn(&v), k)))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Combine the values of the keys which exist in both maps.
+    ///
+    /// Both maps are iterated over only once(merge-join),
+    /// the entries are compared in the storage order of keys.
+    pub fn zip_with<V2, V3, F>(&self, other: &Mapx<K, V2>, f: F) -> Vec<(K, V3)>
+    where
+        V2: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+        F: Fn(V, V2) -> V3,
+    {
+        let key_bytes = |k: &K| pnk!(bincode::serialize(k));
+
+        let mut res = vec![];
+        let mut it0 = self.iter().peekable();
+        let mut it1 = other.iter().peekable();
+
+        while let (Some((k0, _)), Some((k1, _))) = (it0.peek(), it1.peek()) {
+            match key_bytes(k0).cmp(&key_bytes(k1)) {
+                Ordering::Less => {
+                    it0.next();
+                }
+                Ordering::Greater => {
+                    it1.next();
+                }
+                Ordering::Equal => {
+                    let (k, v0) = pnk!(it0.next());
+                    let (_, v1) = pnk!(it1.next());
+                    res.push((k, f(v0, v1)));
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Similar with `zip_with`,
+    /// but the results are written into a new instance at `dest_path`.
+    pub fn zip_with_collect<V2, V3, F>(
+        &self,
+        other: &Mapx<K, V2>,
+        f: F,
+        dest_path: &str,
+    ) -> Result<Mapx<K, V3>>
+    where
+        V2: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+        V3: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+        F: Fn(V, V2) -> V3,
+    {
+        let mut db = Mapx::new(dest_path).c(d!())?;
+        db.in_disk
+            .set_value_batch(self.zip_with(other, f))
+            .c(d!())
+            .map(|_| db)
+    }
+
+    /// Push all the keys into `dest` in the order of `Ord`,
+    /// they are written in one batch, see `to_sorted_vec()`.
+    #[inline(always)]
+    pub fn collect_keys_into(&self, dest: &mut Vecx<K>) {
+        pnk!(dest.push_batch(self.to_sorted_vec().into_iter().map(|(k, _)| k)));
+    }
+
+    /// Push all the values into `dest` in the order of their keys,
+    /// they are written in one batch, see `to_sorted_vec()`.
+    #[inline(always)]
+    pub fn collect_values_into(&self, dest: &mut Vecx<V>) {
+        pnk!(dest.push_batch(self.to_sorted_vec().into_iter().map(|(_, v)| v)));
+    }
+
+    /// Load all the entries into a `HashMap`.
+    #[inline(always)]
+    pub fn to_hashmap(&self) -> HashMap<K, V> {
+        self.iter().collect()
+    }
+
+    /// Create an instance at `path` with the entries of `map`,
+    /// which are written in one batch.
+    #[inline(always)]
+    pub fn from_hashmap(map: HashMap<K, V>, path: &str) -> Result<Self> {
+        Self::from_iter_at_path(map, path).c(d!())
+    }
+
+    /// Load all the entries into memory, sorted by the `Ord` of `K`,
+    /// see the storage notes of this module.
+    pub fn to_sorted_vec(&self) -> Vec<(K, V)> {
+        let mut res = self.iter().collect::<Vec<_>>();
+        res.sort_by(|a, b| a.0.cmp(&b.0));
+        res
+    }
+
+    /// Consume the instance and load all the entries into memory,
+    /// see `to_sorted_vec()`.
+    #[inline(always)]
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        self.to_sorted_vec()
+    }
+
+    /// Consume the instance and load all the keys into memory,
+    /// sorted by the `Ord` of `K`.
+    #[inline(always)]
+    pub fn into_keys(self) -> Vec<K> {
+        self.into_sorted_vec().into_iter().map(|(k, _)| k).collect()
+    }
+
+    /// Consume the instance and load all the values into memory,
+    /// sorted by the `Ord` of their keys.
+    #[inline(always)]
+    pub fn into_values(self) -> Vec<V> {
+        self.into_sorted_vec().into_iter().map(|(_, v)| v).collect()
+    }
+
+    /// Write all the values into a new Vecx at `dest_path`,
+    /// sorted by the `Ord` of their keys, so they can be accessed by index.
+    ///
+    /// The entries are read from one db snapshot, and loaded into memory.
+    #[inline(always)]
+    pub fn snapshot_values_to_vecx(&self, dest_path: &str) -> Result<Vecx<V>> {
+        let entries = self.in_disk.snapshot_sorted_vec().c(d!())?;
+        let mut dest = Vecx::new(dest_path).c(d!())?;
+        dest.push_batch(entries.into_iter().map(|(_, v)| v))
+            .c(d!())?;
+        Ok(dest)
+    }
+
+    /// Write all the keys into a new Vecx at `dest_path`,
+    /// sorted by the `Ord` of `K`, so they can be accessed by index.
+    ///
+    /// The entries are read from one db snapshot, and loaded into memory.
+    #[inline(always)]
+    pub fn snapshot_keys_to_vecx(&self, dest_path: &str) -> Result<Vecx<K>> {
+        let entries = self.in_disk.snapshot_sorted_vec().c(d!())?;
+        let mut dest = Vecx::new(dest_path).c(d!())?;
+        dest.push_batch(entries.into_iter().map(|(k, _)| k))
+            .c(d!())?;
+        Ok(dest)
+    }
+
+    /// Randomly pick at most `n` entries with the reservoir sampling(Algorithm R),
+    /// every entry has the same probability to be picked.
+    ///
+    /// **NOTE**: all the entries will be iterated over once.
+    pub fn random_sample(&self, n: usize) -> Vec<(K, V)> {
+        let mut rng = rand::thread_rng();
+        let mut res = Vec::with_capacity(n);
+
+        for (i, kv) in self.iter().enumerate() {
+            if i < n {
+                res.push(kv);
+            } else {
+                let j = rng.gen_range(0..=i);
+                if j < n {
+                    res[j] = kv;
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Randomly pick a key without iterating over all the entries.
+    ///
+    /// **NOTE**: the result is biased, it is the first key after a random position
+    /// in the storage space, so a key following a larger gap is more likely
+    /// to be picked, use `random_sample(1)` if a uniform distribution is required.
+    #[inline(always)]
+    pub fn random_key(&self) -> Option<K> {
+        self.in_disk.random_key()
+    }
+
+    /// Get at most `limit` keys which are greater than `key`,
+    /// in the ascending order of `K`.
+    ///
+    /// All the entries are scanned, see the storage notes of this module.
+    pub fn keys_after(&self, key: &K, limit: usize) -> Vec<K> {
+        // a max-heap holding the smallest keys
+        let mut heap = BinaryHeap::with_capacity(limit + 1);
+        for (k, _) in self.iter().filter(|(k, _)| k > key) {
+            heap.push(k);
+            if heap.len() > limit {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+    }
+
+    /// Get at most `limit` keys which are less than `key`,
+    /// in the descending order of `K`.
+    ///
+    /// All the entries are scanned, see the storage notes of this module.
+    pub fn keys_before(&self, key: &K, limit: usize) -> Vec<K> {
+        // a min-heap holding the largest keys
+        let mut heap = BinaryHeap::with_capacity(limit + 1);
+        for (k, _) in self.iter().filter(|(k, _)| k < key) {
+            heap.push(Reverse(k));
+            if heap.len() > limit {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec().into_iter().map(|k| k.0).collect()
+    }
+
+    /// Get the entries at the given ranks(0-indexed positions by the `Ord` of `K`),
+    /// `None` will be returned for the ranks beyond the length.
+    ///
+    /// **NOTE**: all the keys are scanned, the entries up to the max rank
+    /// are kept in memory.
+    pub fn pick_by_rank(&self, ranks: &[usize]) -> Vec<Option<(K, V)>> {
+        let max_rank = match ranks.iter().max() {
+            Some(r) => *r,
+            None => return vec![],
+        };
+
+        // the smallest `max_rank + 1` entries
+        let mut kept = BTreeMap::new();
+        for (k, v) in self.iter() {
+            kept.insert(k, v);
+            if kept.len() > max_rank.saturating_add(1) {
+                kept.pop_last();
+            }
+        }
+
+        let kept = kept.into_iter().collect::<Vec<_>>();
+        ranks.iter().map(|r| kept.get(*r).cloned()).collect()
+    }
+
+    /// Remove and return all the entries satisfying `pred`, sorted by the `Ord` of `K`,
+    /// the collection is scanned only once, and the removals are written in one batch.
+    ///
+    /// **NOTE**: `pred` is called in the storage(encoded byte) order.
+    pub fn take_where<F>(&mut self, pred: F) -> Vec<(K, V)>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut res = pnk!(self.in_disk.take_where(pred));
+        res.sort_by(|a, b| a.0.cmp(&b.0));
+        res
+    }
+
+    /// Insert the entry only if the number of entries is less than `max_entries`,
+    /// return if it has been inserted, or the error of the write.
+    ///
+    /// Updating an existing key is always allowed, since it does not grow the size.
+    pub fn bounded_insert(
+        &mut self,
+        key: K,
+        value: V,
+        max_entries: usize,
+    ) -> Result<bool> {
+        if self.len() < max_entries || self.in_disk.contains_key(&key) {
+            if let Some(m) = self.metrics() {
+                MapxMetrics::incr(&m.insert_count);
+            }
+            self.in_disk
+                .set_value_batch([(key, value)])
+                .c(d!())
+                .map(|_| true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Same as `bounded_insert`, but the entries with the smallest keys
+    /// (by the `Ord` of `K`) will be removed until there is room for the new one,
+    /// the removed entries will be returned, sorted by the `Ord` of `K`.
+    ///
+    /// Nothing will be inserted if `max_entries` is 0.
+    ///
+    /// **NOTE**: all the keys are scanned to find the smallest ones.
+    pub fn bounded_insert_evict_oldest(
+        &mut self,
+        key: K,
+        value: V,
+        max_entries: usize,
+    ) -> Vec<(K, V)> {
+        if 0 == max_entries {
+            return vec![];
+        }
+
+        let evicted = if self.len() >= max_entries && !self.in_disk.contains_key(&key) {
+            self.take_first_n(self.len() + 1 - max_entries)
+        } else {
+            vec![]
+        };
+        self.set_value(key, value);
+        evicted
+    }
+
+    /// Remove and return the `n` smallest entries by the `Ord` of `K`,
+    /// the returned entries are sorted by the `Ord` of `K`.
+    ///
+    /// **NOTE**: all the keys are scanned, at most `n` entries are kept in memory.
+    #[inline(always)]
+    pub fn take_first_n(&mut self, n: usize) -> Vec<(K, V)> {
+        pnk!(self.in_disk.take_n_by_ord(n, false))
+    }
+
+    /// Remove and return the `n` largest entries by the `Ord` of `K`,
+    /// the returned entries are sorted by the `Ord` of `K`, see `take_first_n()`.
+    #[inline(always)]
+    pub fn take_last_n(&mut self, n: usize) -> Vec<(K, V)> {
+        pnk!(self.in_disk.take_n_by_ord(n, true))
+    }
+
+    /// Check if a key is exists.
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        if let Some(m) = self.metrics() {
+            MapxMetrics::incr(&m.contains_key_count);
+        }
+        self.in_disk.contains_key(key)
+    }
+
+    /// Check if all the keys exist, `true` will be returned for an empty slice.
+    ///
+    /// The keys are read in one batch, instead of one by one.
+    #[inline(always)]
+    pub fn contains_all(&self, keys: &[K]) -> bool {
+        self.in_disk.contains_keys(keys).into_iter().all(|b| b)
+    }
+
+    /// Check if any of the keys exists, `false` will be returned for an empty slice.
+    ///
+    /// The keys are read in one batch, instead of one by one.
+    #[inline(always)]
+    pub fn contains_any(&self, keys: &[K]) -> bool {
+        self.in_disk.contains_keys(keys).into_iter().any(|b| b)
+    }
+
+    /// Imitate the behavior of 'BTreeSet<_>.is_disjoint(...)' on the keys.
+    pub fn is_disjoint(&self, other: &Mapx<K, V>) -> bool {
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        !small.iter().any(|(k, _)| large.contains_key(&k))
     }
 
-    /// Similar with `insert`, but ignore the old value.
+    /// Imitate the behavior of 'BTreeSet<_>.is_subset(...)' on the keys.
+    pub fn is_subset(&self, other: &Mapx<K, V>) -> bool {
+        if self.len() > other.len() {
+            return false;
+        }
+        self.iter().all(|(k, _)| other.contains_key(&k))
+    }
+
+    /// Imitate the behavior of 'BTreeSet<_>.is_superset(...)' on the keys.
     #[inline(always)]
-    pub fn set_value(&mut self, key: K, value: V) {
-        self.in_disk.set_value(key, value);
+    pub fn is_superset(&self, other: &Mapx<K, V>) -> bool {
+        other.is_subset(self)
     }
 
-    /// Imitate the behavior of '.entry(...).or_insert(...)'
+    /// Remove a <K, V> from mem and disk.
     #[inline(always)]
-    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
-        Entry { key, db: self }
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if let Some(m) = self.metrics() {
+            MapxMetrics::incr(&m.remove_count);
+        }
+        self.in_disk.remove(key)
     }
 
-    /// Imitate the behavior of '.iter()'
+    /// Move the value of `src` to `dest`, the two writes are done in one batch,
+    /// the existing value of `dest` will be overwritten.
+    ///
+    /// The moved value will be returned, or `None` if `src` does not exist.
     #[inline(always)]
-    pub fn iter(&self) -> Box<dyn Iterator<Item = (K, V)> + '_> {
-        Box::new(MapxIter {
-            iter: self.in_disk.iter(),
-        })
+    pub fn move_entry(&mut self, src: &K, dest: K) -> Option<V> {
+        pnk!(self.in_disk.move_entry(src, dest))
     }
 
-    /// Check if a key is exists.
+    /// Apply `f` to the value of `key`, and write it back in one batch,
+    /// unlike `get_mut`, no guard is kept alive during the update.
+    ///
+    /// Return `false` if the key does not exist, `f` will not be called in this case.
+    ///
+    /// **NOTE**: an error is returned if the stored value is changed while `f` runs,
+    /// see `atomic_rmw` for the limits of this check.
     #[inline(always)]
-    pub fn contains_key(&self, key: &K) -> bool {
-        self.in_disk.contains_key(key)
+    pub fn update_in_place<F>(&mut self, key: &K, f: F) -> Result<bool>
+    where
+        F: FnOnce(&mut V),
+    {
+        self.in_disk.update_in_place(key, f).c(d!())
     }
 
-    /// Remove a <K, V> from mem and disk.
+    /// Read the value of `key`, pass it to `f`,
+    /// then write the result back, `None` means removing the key.
+    ///
+    /// The raw value is read again before the write, under a lock of the key,
+    /// if it differs from the first read(e.g. changed by a clone in another thread),
+    /// nothing will be written and an error will be returned,
+    /// the caller can retry in this case.
+    ///
+    /// The old value will be returned.
+    ///
+    /// **NOTE**:
+    /// - no snapshot or transaction is used, only the calls of this method
+    ///   and `update_in_place` take the lock, so a plain write(e.g. `insert`)
+    ///   between the second read and the write is lost
+    /// - a value changed and then changed back before the second read
+    ///   is not regarded as a conflict
+    /// - use the clones of one instance for concurrent calls, they share one length,
+    ///   other instances opened on the same path keep their own lengths
     #[inline(always)]
-    pub fn remove(&mut self, key: &K) -> Option<V> {
-        self.in_disk.remove(key)
+    pub fn atomic_rmw<F>(&mut self, key: K, f: F) -> Result<Option<V>>
+    where
+        F: FnOnce(Option<V>) -> Option<V>,
+    {
+        self.in_disk.atomic_rmw(key, f).c(d!())
     }
 
     /// Remove a <K, V> from mem and disk.
     #[inline(always)]
     pub fn unset_value(&mut self, key: &K) {
+        if let Some(m) = self.metrics() {
+            MapxMetrics::incr(&m.remove_count);
+        }
         self.in_disk.unset_value(key);
     }
 }
@@ -392,6 +2200,7 @@ where
     V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
 {
     iter: backend::MapxIter<'a, K, V>,
+    len: usize,
 }
 
 impl<'a, K, V> Iterator for MapxIter<'a, K, V>
@@ -401,8 +2210,122 @@ where
 {
     type Item = (K, V);
     fn next(&mut self) -> Option<Self::Item> {
+        if 0 == self.len {
+            return None;
+        }
+        self.len -= 1;
         self.iter.next()
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+// The length is captured when the iterator is created,
+// the collection can not be changed during the iteration.
+impl<'a, K, V> ExactSizeIterator for MapxIter<'a, K, V>
+where
+    K: Clone + PartialEq + Eq + Hash + Serialize + DeserializeOwned + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+}
+
+impl<'a, K, V> FusedIterator for MapxIter<'a, K, V>
+where
+    K: Clone + PartialEq + Eq + Hash + Serialize + DeserializeOwned + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+}
+
+/// Iter over [Mapx](self::Mapx), with a callback on each entry,
+/// see `Mapx::tap_iter()`.
+pub struct TappedMapxIter<'a, K, V, F>
+where
+    K: Clone + PartialEq + Eq + Hash + Serialize + DeserializeOwned + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+    F: FnMut(&K, &V),
+{
+    iter: MapxIter<'a, K, V>,
+    f: F,
+}
+
+impl<'a, K, V, F> Iterator for TappedMapxIter<'a, K, V, F>
+where
+    K: Clone + PartialEq + Eq + Hash + Serialize + DeserializeOwned + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+    F: FnMut(&K, &V),
+{
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, v) = self.iter.next()?;
+        (self.f)(&k, &v);
+        Some((k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, K, V, F> ExactSizeIterator for TappedMapxIter<'a, K, V, F>
+where
+    K: Clone + PartialEq + Eq + Hash + Serialize + DeserializeOwned + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+    F: FnMut(&K, &V),
+{
+}
+
+impl<'a, K, V, F> FusedIterator for TappedMapxIter<'a, K, V, F>
+where
+    K: Clone + PartialEq + Eq + Hash + Serialize + DeserializeOwned + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+    F: FnMut(&K, &V),
+{
+}
+
+/// An owning iterator over [Mapx](self::Mapx), see `Mapx::into_streaming_iter()`,
+/// the entries are read and decoded one by one.
+pub struct StreamingMapxIter<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    iter: DBIterator<'static>,
+    prefix_len: usize,
+    _hdr: Mapx<K, V>,
+}
+
+impl<K, V> Iterator for StreamingMapxIter<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Item = Result<(K, V)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k, v)| {
+            Ok((
+                bincode::deserialize(&k[self.prefix_len..]).c(d!())?,
+                serde_json::from_slice(&v).c(d!())?,
+            ))
+        })
+    }
 }
 
 /**********************************************/
@@ -432,10 +2355,154 @@ where
 // End of the implementation of Eq for Mapx //
 ///////////////////////////////////////////////////////
 
+////////////////////////////////////////////////////////
+// Begin of the implementation of TryFrom for Mapx //
+/******************************************************/
+
+impl<K, V> TryFrom<Mapx<K, V>> for BTreeMap<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Error = Box<dyn RucError>;
+
+    fn try_from(m: Mapx<K, V>) -> Result<Self> {
+        Ok(m.iter().collect())
+    }
+}
+
+// The data will be written to a Mapx at the given path,
+// the existing entries of the path will be kept.
+impl<K, V> TryFrom<(BTreeMap<K, V>, &str)> for Mapx<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Error = Box<dyn RucError>;
+
+    fn try_from((m, path): (BTreeMap<K, V>, &str)) -> Result<Self> {
+        let mut hdr = Mapx::new(path).c(d!())?;
+        hdr.in_disk.set_value_batch(m).c(d!())?;
+        Ok(hdr)
+    }
+}
+
+/****************************************************/
+// End of the implementation of TryFrom for Mapx //
+//////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////
+// Begin of the implementation of Add for Mapx //
+/**************************************************/
+
+// A new instance will be created at a unique path,
+// the values of `rhs` win on conflict.
+impl<K, V> Add<&Mapx<K, V>> for &Mapx<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Output = Mapx<K, V>;
+
+    fn add(self, rhs: &Mapx<K, V>) -> Mapx<K, V> {
+        let mut db = pnk!(Mapx::new(&crate::unique_path!()));
+        pnk!(db.in_disk.set_value_batch(self.iter().chain(rhs.iter())));
+        db
+    }
+}
+
+impl<K, V> Add for Mapx<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Output = Mapx<K, V>;
+
+    #[inline(always)]
+    fn add(self, rhs: Mapx<K, V>) -> Mapx<K, V> {
+        &self + &rhs
+    }
+}
+
+// The values of `rhs` win on conflict.
+impl<K, V> AddAssign<&Mapx<K, V>> for Mapx<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    fn add_assign(&mut self, rhs: &Mapx<K, V>) {
+        pnk!(self.in_disk.set_value_batch(rhs.iter()));
+    }
+}
+
+impl<K, V> AddAssign for Mapx<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Mapx<K, V>) {
+        *self += &rhs;
+    }
+}
+
+/************************************************/
+// End of the implementation of Add for Mapx //
+//////////////////////////////////////////////////
+
 ///////////////////////////////////////////////////////////////////
 // Begin of the implementation of Serialize/Deserialize for Mapx //
 /*****************************************************************/
 
+#[cfg(not(feature = "serialize-full"))]
 impl<K, V> serde::Serialize for Mapx<K, V>
 where
     K: Clone
@@ -453,14 +2520,17 @@ where
     where
         S: serde::Serializer,
     {
+        let ns = self.in_disk.namespace();
         let v = pnk!(serde_json::to_string(&CacheMeta {
             path: self.get_path(),
+            namespace: alt!(ns.is_empty(), None, Some(ns.to_vec())),
         }));
 
         serializer.serialize_str(&v)
     }
 }
 
+#[cfg(not(feature = "serialize-full"))]
 impl<'de, K, V> serde::Deserialize<'de> for Mapx<K, V>
 where
     K: Clone
@@ -480,11 +2550,65 @@ where
     {
         deserializer.deserialize_str(CacheVisitor).map(|meta| {
             let meta = pnk!(serde_json::from_str::<CacheMeta>(&meta));
-            pnk!(Mapx::new(meta.path))
+            match meta.namespace {
+                Some(ns) => pnk!(Mapx::with_namespace_prefix(meta.path, ns)),
+                None => pnk!(Mapx::new(meta.path)),
+            }
         })
     }
 }
 
+// Serialize all the entries as a map,
+// so the data can be transported to other processes.
+#[cfg(feature = "serialize-full")]
+impl<K, V> serde::Serialize for Mapx<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(self.iter())
+    }
+}
+
+// A new instance will be created at a unique path.
+#[cfg(feature = "serialize-full")]
+impl<'de, K, V> serde::Deserialize<'de> for Mapx<K, V>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+    V: Clone + PartialEq + Serialize + DeserializeOwned + fmt::Debug,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data = <BTreeMap<K, V> as serde::Deserialize>::deserialize(deserializer)?;
+        let mut db = Mapx::new(&crate::unique_path!())
+            .map_err(|e| serde::de::Error::custom(e.to_string()))?;
+        data.into_iter().for_each(|(k, v)| db.set_value(k, v));
+        Ok(db)
+    }
+}
+
 /***************************************************************/
 // End of the implementation of Serialize/Deserialize for Mapx //
 /////////////////////////////////////////////////////////////////
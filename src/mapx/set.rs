@@ -0,0 +1,86 @@
+//!
+//! # A disk-storage replacement for the pure in-memory BTreeSet
+//!
+
+use super::Mapx;
+use ruc::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, hash::Hash};
+
+/// To solve the problem of unlimited memory usage,
+/// use this to replace the original in-memory `BTreeSet<_>`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MapxSet<K>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+{
+    inner: Mapx<K, ()>,
+}
+
+impl<K> MapxSet<K>
+where
+    K: Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned
+        + fmt::Debug,
+{
+    /// Create an instance.
+    #[inline(always)]
+    pub fn new(path: &str) -> Result<Self> {
+        Mapx::new(path).c(d!()).map(|inner| MapxSet { inner })
+    }
+
+    /// Get the database storage path
+    pub fn get_path(&self) -> &str {
+        self.inner.get_path()
+    }
+
+    /// Imitate the behavior of 'BTreeSet<_>.len()'.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// A helper func
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Imitate the behavior of 'BTreeSet<_>.insert(...)'.
+    #[inline(always)]
+    pub fn insert(&mut self, key: K) -> bool {
+        self.inner.insert(key, ()).is_none()
+    }
+
+    /// Imitate the behavior of 'BTreeSet<_>.contains(...)'.
+    #[inline(always)]
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Imitate the behavior of 'BTreeSet<_>.remove(...)'.
+    #[inline(always)]
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.inner.remove(key).is_some()
+    }
+
+    /// Imitate the behavior of 'BTreeSet<_>.iter()'.
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = K> + '_ {
+        self.inner.iter().map(|(k, _)| k)
+    }
+}
@@ -4,7 +4,7 @@
 
 use super::*;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::{collections::HashSet, fs};
 
 #[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
 struct SampleBlock {
@@ -26,7 +26,12 @@ fn t_mapx() {
     let cnt = 200;
 
     let db = {
-        omit!(fs::remove_dir_all("/tmp/bnc_test/Mapx"));
+        // the meta of the path is kept by `crate::clear()`
+        omit!(fs::remove_dir_all(format!(
+            "{}/{}//tmp/bnc_test/Mapx",
+            crate::BNC_DATA_DIR.as_str(),
+            crate::BNC_META_NAME
+        )));
         let mut dbi = crate::new_mapx!("/tmp/bnc_test/Mapx");
 
         assert_eq!(0, dbi.len());
@@ -70,3 +75,2513 @@ fn t_mapx() {
     crate::clear();
     assert!(db_restore.is_empty());
 }
+
+#[test]
+fn t_approximate_size() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..1000usize).for_each(|i| db.set_value(i, gen_sample(i)));
+    crate::flush_data();
+
+    assert!(0 < db.approximate_size_bytes());
+    assert!(0 < db.approximate_memory_bytes());
+}
+
+#[test]
+fn t_key_set_cmp() {
+    crate::clear();
+
+    let mut a = crate::new_mapx!();
+    let mut b = crate::new_mapx!();
+    let mut c = crate::new_mapx!();
+
+    (0..20usize).for_each(|i| a.set_value(i, gen_sample(i)));
+    (5..10usize).for_each(|i| b.set_value(i, gen_sample(i)));
+    (20..30usize).for_each(|i| c.set_value(i, gen_sample(i)));
+
+    assert!(!a.is_disjoint(&b));
+    assert!(a.is_disjoint(&c));
+    assert!(c.is_disjoint(&a));
+
+    assert!(b.is_subset(&a));
+    assert!(!a.is_subset(&b));
+    assert!(!c.is_subset(&a));
+
+    assert!(a.is_superset(&b));
+    assert!(!b.is_superset(&a));
+}
+
+#[test]
+fn t_try_insert() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+
+    assert!(db.try_insert(1usize, gen_sample(1)).is_ok());
+    assert_eq!(db.try_insert(1, gen_sample(2)), Err((1, gen_sample(2))));
+    assert_eq!(pnk!(db.get(&1)), gen_sample(1));
+
+    assert!(db.try_insert(2, gen_sample(2)).is_ok());
+    assert_eq!(2, db.len());
+}
+
+#[test]
+fn t_conditional_insert() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+
+    assert!(db.put_if_absent(1usize, gen_sample(1)));
+    assert!(!db.put_if_absent(1, gen_sample(2)));
+    assert_eq!(pnk!(db.get(&1)), gen_sample(1));
+
+    assert!(db.replace_if_present(&2, gen_sample(2)).is_none());
+    assert!(!db.contains_key(&2));
+    assert_eq!(
+        db.replace_if_present(&1, gen_sample(3)),
+        Some(gen_sample(1))
+    );
+    assert_eq!(pnk!(db.get(&1)), gen_sample(3));
+    assert_eq!(1, db.len());
+}
+
+#[test]
+fn t_swap_value() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+
+    assert!(db.swap_value(&0usize, "hello".to_owned()).is_none());
+    assert_eq!(
+        db.swap_value(&0, "world".to_owned()),
+        Some("hello".to_owned())
+    );
+    assert_eq!(db.get(&0), Some("world".to_owned()));
+}
+
+#[cfg(feature = "serialize-full")]
+#[test]
+fn t_serialize_full() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100usize).for_each(|i| db.set_value(i, gen_sample(i)));
+
+    let json = pnk!(serde_json::to_string(&db));
+    let value = pnk!(serde_json::from_str::<serde_json::Value>(&json));
+    assert_eq!(100, pnk!(value.as_object()).len());
+
+    let db_restore = pnk!(serde_json::from_str::<Mapx<usize, SampleBlock>>(&json));
+    assert_ne!(db.get_path(), db_restore.get_path());
+    assert_eq!(100, db_restore.len());
+    (0..100).for_each(|i| {
+        assert_eq!(db_restore.get(&i), Some(gen_sample(i)));
+    });
+}
+
+#[test]
+fn t_aggregation() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100usize).for_each(|i| db.set_value(i, i));
+    assert_eq!(50, db.count_matching(|k, _| 0 == k % 2));
+    assert_eq!(0, db.count_matching(|k, _| 100 < *k));
+
+    let mut db = crate::new_mapx!();
+    db.set_value("alice".to_owned(), 1usize);
+    db.set_value("bob".to_owned(), 10);
+    db.set_value("carol".to_owned(), 100);
+    assert_eq!(101, db.sum_matching(|k, _| k.contains('a'), |v| *v));
+    assert_eq!(0, db.sum_matching(|_, _| false, |v| *v));
+}
+
+#[test]
+fn t_update_batch() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100usize).for_each(|i| db.set_value(i, i));
+
+    pnk!(db.update_batch(|_, v| Some(v * 2)));
+    assert_eq!(100, db.len());
+    (0..100).for_each(|i| {
+        assert_eq!(db.get(&i), Some(i * 2));
+    });
+
+    pnk!(db.update_batch(|k, v| alt!(0 == k % 2, Some(v), None)));
+    assert_eq!(50, db.len());
+    assert!(db.iter().all(|(k, _)| 0 == k % 2));
+}
+
+#[test]
+fn t_filter_collect() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..1000usize).for_each(|i| db.set_value(i, gen_sample(i)));
+
+    let evens = pnk!(db.filter_collect(|k, _| 0 == k % 2, &crate::unique_path!()));
+    assert_eq!(500, evens.len());
+    assert!(evens.iter().all(|(k, v)| 0 == k % 2 && v == gen_sample(k)));
+
+    let idxs = pnk!(db.map_collect(|k, v| (k, v.idx), &crate::unique_path!()));
+    assert_eq!(1000, idxs.len());
+    assert!(idxs.iter().all(|(k, v)| k == v));
+}
+
+#[test]
+fn t_take_n() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100u8).for_each(|i| db.set_value(i, gen_sample(i as usize)));
+
+    let res = db.take_first_n(10);
+    assert_eq!((0..10).collect::<Vec<u8>>(), keys_of(&res));
+    assert_eq!(90, db.len());
+    assert!(!db.contains_key(&9));
+
+    let res = db.take_first_n(10);
+    assert_eq!((10..20).collect::<Vec<u8>>(), keys_of(&res));
+    assert_eq!(80, db.len());
+
+    let res = db.take_last_n(10);
+    assert_eq!((90..100).collect::<Vec<u8>>(), keys_of(&res));
+    assert_eq!(70, db.len());
+    assert!(db.contains_key(&89));
+
+    assert!(db.take_first_n(0).is_empty());
+    assert_eq!(70, db.take_last_n(1000).len());
+    assert!(db.is_empty());
+
+    // multi-byte keys, the storage order differs from the `Ord`
+    let mut db = crate::new_mapx!();
+    (250..262u32).for_each(|i| db.set_value(i, i));
+    assert_eq!(vec![(250, 250), (251, 251)], db.take_first_n(2));
+    assert_eq!(vec![(260, 260), (261, 261)], db.take_last_n(2));
+    assert_eq!(vec![(252, 252), (253, 253)], db.take_first_n(2));
+    assert_eq!(6, db.len());
+    assert_eq!(
+        (254..260).map(|i| (i, i)).collect::<Vec<_>>(),
+        db.to_sorted_vec()
+    );
+}
+
+fn keys_of(entries: &[(u8, SampleBlock)]) -> Vec<u8> {
+    entries.iter().map(|(k, _)| *k).collect()
+}
+
+#[test]
+fn t_random_sample() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert!(db.random_key().is_none());
+    assert!(db.random_sample(10).is_empty());
+
+    (0..10000usize).for_each(|i| db.set_value(i, gen_sample(i)));
+
+    let res = db.random_sample(100);
+    assert_eq!(100, res.len());
+    assert_eq!(
+        100,
+        res.iter().map(|(k, _)| *k).collect::<HashSet<_>>().len()
+    );
+    res.iter().for_each(|(k, v)| assert_eq!(&gen_sample(*k), v));
+
+    assert_eq!(10000, db.random_sample(20000).len());
+
+    for _ in 0..10 {
+        assert!(db.contains_key(&db.random_key().unwrap()));
+    }
+}
+
+#[test]
+fn t_fragmentation_ratio() {
+    crate::clear();
+
+    // 1000 entries are inserted, and 800 of them are deleted
+    assert_eq!(0.8, backend::fragmentation(200, 800));
+    assert_eq!(0.0, backend::fragmentation(1000, 0));
+    assert_eq!(1.0, backend::fragmentation(0, 800));
+    assert_eq!(0.0, backend::fragmentation(0, 0));
+
+    let mut db = crate::new_mapx!();
+    (0..1000usize).for_each(|i| db.set_value(i, gen_sample(i)));
+    (0..800usize).for_each(|i| db.unset_value(&i));
+
+    // the db instance is shared by the other tests running at the same time,
+    // and may be compacted by them, so only the range is checked
+    let ratio = db.fragmentation_ratio();
+    assert!((0.0..=1.0).contains(&ratio));
+    assert!((0.0..=1.0).contains(&db.live_entry_ratio()));
+}
+
+#[test]
+fn t_mapx_set() {
+    crate::clear();
+
+    let mut set = crate::new_mapx_set!();
+    assert!(set.is_empty());
+    assert!(set.insert(1u32));
+    assert!(!set.insert(1u32));
+    assert!(set.insert(2u32));
+    assert_eq!(2, set.len());
+    assert!(set.contains(&1));
+    assert!(set.remove(&1));
+    assert!(!set.remove(&1));
+    assert_eq!(vec![2], set.iter().collect::<Vec<_>>());
+
+    let mut set = crate::new_mapx_set!(@String);
+    assert!(set.insert("a".to_owned()));
+    assert!(set.contains(&"a".to_owned()));
+
+    let path = "t_mapx_set";
+    {
+        let mut set = crate::new_mapx_set!(path);
+        assert!(set.insert(9usize));
+    }
+    let set: MapxSet<usize> = crate::new_mapx_set!(path);
+    assert!(set.contains(&9));
+}
+
+#[test]
+fn t_iter_len() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert_eq!(0, db.iter_exact().len());
+
+    (0..100usize).for_each(|i| db.set_value(i, gen_sample(i)));
+
+    let mut it = db.iter_exact();
+    assert_eq!(100, it.len());
+    (0..40).for_each(|_| assert!(it.next().is_some()));
+    assert_eq!(60, it.len());
+    assert_eq!(60, it.by_ref().count());
+    assert_eq!(0, it.len());
+    assert!(it.next().is_none());
+    assert!(it.next().is_none());
+}
+
+#[test]
+fn t_pick_by_rank() {
+    crate::clear();
+
+    // the ranks follow the `Ord` of the keys, not the storage order
+    let mut db = crate::new_mapx!();
+    [1u32, 256, 2, 511]
+        .iter()
+        .for_each(|i| db.set_value(*i, *i));
+    assert_eq!(
+        vec![
+            Some((2, 2)),
+            None,
+            Some((1, 1)),
+            Some((511, 511)),
+            Some((256, 256))
+        ],
+        db.pick_by_rank(&[1, 4, 0, 3, 2])
+    );
+
+    let mut db = crate::new_mapx!();
+    assert_eq!(vec![None], db.pick_by_rank(&[0]));
+
+    (0..1000usize).for_each(|i| db.set_value(i, gen_sample(i)));
+    let all = db.to_sorted_vec();
+
+    let res = db.pick_by_rank(&[0, 499, 999]);
+    assert_eq!(
+        vec![
+            Some(all[0].clone()),
+            Some(all[499].clone()),
+            Some(all[999].clone())
+        ],
+        res
+    );
+
+    let res = db.pick_by_rank(&[1000, 7, 7, 3]);
+    assert_eq!(
+        vec![
+            None,
+            Some(all[7].clone()),
+            Some(all[7].clone()),
+            Some(all[3].clone())
+        ],
+        res
+    );
+
+    assert!(db.pick_by_rank(&[]).is_empty());
+}
+
+#[test]
+fn t_unique_path_in() {
+    crate::clear();
+
+    let p0 = crate::unique_path_in!("t_unique_path_in_0");
+    let p1 = crate::unique_path_in!("t_unique_path_in_1");
+    assert_ne!(p0, p1);
+    assert_ne!(p0, crate::unique_path_in!("t_unique_path_in_0"));
+
+    let ns_root = format!(
+        "{}/{}/t_unique_path_in_0",
+        crate::BNC_DATA_DIR.as_str(),
+        crate::BNC_META_NAME
+    );
+    let paths = (0..3)
+        .map(|_| crate::unique_path_in!("t_unique_path_in_0"))
+        .collect::<Vec<_>>();
+    paths.iter().for_each(|p| {
+        let mut db = pnk!(Mapx::new(p));
+        db.insert(0usize, gen_sample(0));
+        assert!(fs::metadata(format!("{}/{}", crate::BNC_DATA_DIR.as_str(), p)).is_ok());
+    });
+
+    pnk!(fs::remove_dir_all(&ns_root));
+    paths.iter().for_each(|p| {
+        assert!(
+            fs::metadata(format!("{}/{}", crate::BNC_DATA_DIR.as_str(), p)).is_err()
+        );
+    });
+}
+
+#[test]
+#[cfg(feature = "merge-operator")]
+fn t_merge_operator() {
+    crate::clear();
+
+    fn adder(
+        _key: &[u8],
+        existing_val: Option<&[u8]>,
+        operands: &mut rocksdb::MergeOperands,
+    ) -> Option<Vec<u8>> {
+        let mut n = existing_val
+            .map(|v| serde_json::from_slice::<i64>(v).ok())
+            .unwrap_or(Some(0))?;
+        for op in operands {
+            n += serde_json::from_slice::<i64>(op).ok()?;
+        }
+        serde_json::to_vec(&n).ok()
+    }
+
+    let path = crate::unique_path!();
+    let mut db = pnk!(Mapx::with_merge_operator(&path, "adder", adder));
+    (0..10).for_each(|_| pnk!(db.merge(0u32, 1i64)));
+    assert_eq!(Some(10), db.get(&0));
+    assert_eq!(1, db.len());
+
+    db.insert(1, 100);
+    pnk!(db.merge(1, -1));
+    assert_eq!(Some(99), db.get(&1));
+    assert_eq!(2, db.len());
+
+    assert!(Mapx::<u32, i64>::with_merge_operator(&path, "adder", adder).is_ok());
+    assert!(Mapx::<u32, i64>::with_merge_operator(&path, "other", adder).is_err());
+
+    // no merge operator has been registered for this instance
+    let mut plain = crate::new_mapx!();
+    assert!(plain.merge(0u32, 1i64).is_err());
+    assert!(plain.get(&0).is_none());
+
+    // the name is saved, so it is checked after a restart
+    pnk!(crate::helper::MERGE_OPS.write()).clear();
+    assert!(Mapx::<u32, i64>::with_merge_operator(&path, "other", adder).is_err());
+    assert!(pnk!(Mapx::<u32, i64>::new(&path)).merge(0, 1).is_err());
+    let mut db = pnk!(Mapx::with_merge_operator(&path, "adder", adder));
+    pnk!(db.merge(0u32, 1i64));
+    assert_eq!(Some(11), db.get(&0));
+
+    // the operator is kept after renaming to the same or other db instances
+    for i in 0..16 {
+        let new_path = format!("{}_renamed_{}", path, i);
+        pnk!(db.rename(&new_path));
+        pnk!(db.merge(0u32, 1i64));
+        assert_eq!(Some(12 + i), db.get(&0));
+        assert!(
+            Mapx::<u32, i64>::with_merge_operator(&new_path, "other", adder).is_err()
+        );
+    }
+}
+
+#[test]
+fn t_zip_with() {
+    crate::clear();
+
+    let mut db0 = crate::new_mapx!();
+    let mut db1 = crate::new_mapx!();
+    (0..1000u32).for_each(|i| {
+        db0.insert(i, i as u64);
+    });
+    (500..2000u32).step_by(2).for_each(|i| {
+        db1.insert(i, 1u64);
+    });
+
+    let mut res = db0.zip_with(&db1, |a, b| a + b);
+    res.sort_unstable();
+    assert_eq!(
+        (500..1000u32)
+            .step_by(2)
+            .map(|i| (i, 1 + i as u64))
+            .collect::<Vec<_>>(),
+        res
+    );
+
+    let db = pnk!(db0.zip_with_collect(&db1, |a, b| a * b, &crate::unique_path!()));
+    assert_eq!(250, db.len());
+    assert_eq!(Some(998), db.get(&998));
+    assert!(db.get(&999).is_none());
+}
+
+#[test]
+fn t_rename() {
+    crate::clear();
+
+    for i in 0..16usize {
+        let path_a = crate::unique_path!();
+        let path_b = format!("{}_renamed_{}", path_a, i);
+
+        let mut db = pnk!(Mapx::new(&path_a));
+        (0..100usize).for_each(|j| db.set_value(j, gen_sample(j)));
+
+        pnk!(db.rename(&path_b));
+        assert_eq!(path_b, db.get_path());
+        assert_eq!(100, db.len());
+        assert_eq!(Some(gen_sample(9)), db.get(&9));
+        assert!(
+            fs::metadata(format!("{}/{}", crate::BNC_DATA_DIR.as_str(), path_a))
+                .is_err()
+        );
+
+        let db: Mapx<usize, SampleBlock> = pnk!(Mapx::new(&path_b));
+        assert_eq!(100, db.len());
+        assert_eq!(Some(gen_sample(99)), db.get(&99));
+    }
+
+    // the schema version is kept, so no migration is run again
+    for i in 0..16usize {
+        let path_a = crate::unique_path!();
+        let path_b = format!("{}_renamed_{}", path_a, i);
+
+        let mut db = pnk!(Mapx::open_with_version(&path_a, 3, |_, _| Ok(())));
+        db.set_value(i, i);
+        pnk!(db.rename(&path_b));
+
+        let db: Mapx<usize, usize> =
+            pnk!(Mapx::open_with_version(&path_b, 3, |_, _| -> Result<()> {
+                panic!("should not be called")
+            }));
+        assert_eq!(Some(i), db.get(&i));
+    }
+
+    // the destinations are hashed to the same or other db instances
+    let mut db0: Mapx<usize, SampleBlock> = crate::new_mapx!();
+    db0.set_value(0, gen_sample(0));
+    for _ in 0..16 {
+        let mut db1: Mapx<usize, SampleBlock> = crate::new_mapx!();
+        db1.set_value(1, gen_sample(1));
+        let path0 = db0.get_path().to_owned();
+
+        assert!(db0.rename(db1.get_path()).is_err());
+        assert_eq!(path0, db0.get_path());
+        assert_eq!(1, db0.len());
+        assert_eq!(Some(gen_sample(0)), db0.get(&0));
+        assert_eq!(1, db1.len());
+        assert_eq!(Some(gen_sample(1)), db1.get(&1));
+    }
+}
+
+#[test]
+fn t_collect_into() {
+    crate::clear();
+
+    // the storage order differs from the `Ord` of the keys above 255
+    let mut db = crate::new_mapx!();
+    (0..1000usize).for_each(|i| db.set_value(i, gen_sample(i)));
+    let (first_key, first_value) = pnk!(db.peek_first());
+
+    let mut keys = crate::new_vecx!();
+    db.collect_keys_into(&mut keys);
+    assert_eq!(1000, keys.len());
+    assert_eq!(Some(first_key), keys.get(0));
+    (0..1000usize).for_each(|i| assert_eq!(Some(i), keys.get(i)));
+
+    let mut values = crate::new_vecx!();
+    values.push(gen_sample(10000));
+    db.collect_values_into(&mut values);
+    assert_eq!(1001, values.len());
+    assert_eq!(Some(first_value), values.get(1));
+    db.to_sorted_vec()
+        .into_iter()
+        .enumerate()
+        .for_each(|(i, (_, v))| assert_eq!(Some(v), values.get(1 + i)));
+}
+
+#[test]
+fn t_invert() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100u32).for_each(|i| {
+        db.insert(i, format!("v{}", i));
+    });
+
+    let inverted = pnk!(db.invert(&crate::unique_path!()));
+    assert_eq!(100, inverted.len());
+    (0..100u32).for_each(|i| {
+        assert_eq!(Some(i), inverted.get(&format!("v{}", i)));
+    });
+
+    let mut db = crate::new_mapx!();
+    (0..10u8).for_each(|i| {
+        db.insert(i, i % 2);
+    });
+    let inverted = pnk!(db.invert(&crate::unique_path!()));
+    assert_eq!(2, inverted.len());
+    assert_eq!(Some(8), inverted.get(&0));
+    assert_eq!(Some(9), inverted.get(&1));
+}
+
+#[test]
+fn t_validate_invariants() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let mut db = pnk!(Mapx::new(&path));
+    (0..100u32).for_each(|i| {
+        db.insert(i, i as u64);
+    });
+
+    let report = db.validate_invariants();
+    assert!(report.is_valid());
+    assert_eq!(100, report.len);
+    assert_eq!(100, report.valid_entries);
+
+    // write some values with a different type
+    let mut corrupter: Mapx<u32, String> = pnk!(Mapx::new(&path));
+    corrupter.set_value(7, "not a number".to_owned());
+    corrupter.set_value(8, "not a number".to_owned());
+
+    let report = db.validate_invariants();
+    assert!(!report.is_valid());
+    assert_eq!(100, report.entries);
+    assert_eq!(98, report.valid_entries);
+    assert_eq!(2, report.invalid_values);
+    assert_eq!(0, report.invalid_keys);
+
+    let db: Mapx<String, u64> = pnk!(Mapx::new(&path));
+    let report = db.validate_invariants();
+    assert_eq!(100, report.invalid_keys);
+    assert_eq!(0, report.valid_entries);
+}
+
+#[test]
+fn t_compact_all() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..10000usize).for_each(|i| db.set_value(i, gen_sample(i)));
+    (0..9000usize).for_each(|i| db.unset_value(&i));
+    assert!(db.should_compact());
+
+    pnk!(db.compact_all());
+    assert!(db.fragmentation_ratio() < 0.1);
+    assert!(!db.should_compact());
+    assert_eq!(1000, db.len());
+    (9000..10000usize).for_each(|i| assert_eq!(Some(gen_sample(i)), db.get(&i)));
+
+    (0..1000usize).for_each(|i| db.unset_value(&i));
+    pnk!(db.compact_with_timeout(Duration::from_secs(60)));
+    assert_eq!(1000, db.len());
+}
+
+#[test]
+#[cfg(feature = "async")]
+fn t_subscribe() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    let mut rx = db.subscribe();
+
+    (0..5u32).for_each(|i| {
+        db.insert(i, i * 10);
+    });
+    db.remove(&0);
+    db.remove(&100);
+
+    (0..5u32).for_each(|i| {
+        assert_eq!(MapxEvent::Inserted(i, i * 10), pnk!(rx.try_recv()));
+    });
+    assert_eq!(MapxEvent::Removed(0), pnk!(rx.try_recv()));
+    assert!(rx.try_recv().is_err());
+
+    // the batch operations publish events too
+    db.retain_keys(|k| 3 > *k);
+    assert_eq!(MapxEvent::Removed(3), pnk!(rx.try_recv()));
+    assert_eq!(MapxEvent::Removed(4), pnk!(rx.try_recv()));
+    pnk!(db.atomic_swap_all(vec![(7, 70)]));
+    assert_eq!(MapxEvent::Cleared, pnk!(rx.try_recv()));
+    assert_eq!(MapxEvent::Inserted(7, 70), pnk!(rx.try_recv()));
+    assert!(rx.try_recv().is_err());
+
+    // the channel is shared by the clones
+    let mut db2 = db.clone();
+    db2.remove(&7);
+    assert_eq!(MapxEvent::Removed(7), pnk!(rx.try_recv()));
+}
+
+#[test]
+fn t_change_log() {
+    crate::clear();
+
+    let log_path = format!(
+        "{}/{}.log",
+        crate::BNC_DATA_DIR.as_str(),
+        rand::random::<u32>()
+    );
+    let mut db = pnk!(Mapx::with_change_log(&crate::unique_path!(), &log_path));
+    (0..10u32).for_each(|i| {
+        db.insert(i, i.to_string());
+    });
+    (0..3u32).for_each(|i| {
+        db.remove(&i);
+    });
+    db.insert(9, "nine".to_owned());
+    // the batch operations are logged too
+    db.retain_keys(|k| 8 != *k);
+    pnk!(db.flush());
+
+    let log = pnk!(fs::read_to_string(&log_path));
+    let lines = log.lines().collect::<Vec<_>>();
+    assert_eq!(15, lines.len());
+    assert!(lines[14].starts_with(r#"{"op":"remove","key":8,"ts":"#));
+    assert!(lines[0].starts_with(r#"{"op":"insert","key":0,"value":"0","ts":"#));
+    assert!(lines[10].starts_with(r#"{"op":"remove","key":0,"ts":"#));
+
+    let replayed: Mapx<u32, String> = pnk!(Mapx::replay_change_log(
+        std::path::Path::new(&log_path),
+        &crate::unique_path!()
+    ));
+    assert_eq!(6, replayed.len());
+    assert_eq!(
+        db.iter().collect::<Vec<_>>(),
+        replayed.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(Some("nine".to_owned()), replayed.get(&9));
+
+    pnk!(db.atomic_swap_all(vec![(100, "100".to_owned())]));
+    pnk!(db.flush());
+    let log = pnk!(fs::read_to_string(&log_path));
+    let lines = log.lines().collect::<Vec<_>>();
+    assert!(lines[15].starts_with(r#"{"op":"clear","ts":"#));
+    let replayed: Mapx<u32, String> = pnk!(Mapx::replay_change_log(
+        std::path::Path::new(&log_path),
+        &crate::unique_path!()
+    ));
+    assert_eq!(vec![(100, "100".to_owned())], replayed.to_sorted_vec());
+
+    omit!(fs::remove_file(&log_path));
+}
+
+#[test]
+fn t_iter_enumerated() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..1000usize)
+        .rev()
+        .for_each(|i| db.set_value(i, gen_sample(i)));
+
+    // the ranks follow the `Ord` of `K`, not the little-endian bytes
+    let res = db.iter_enumerated().collect::<Vec<_>>();
+    assert_eq!(1000, res.len());
+    res.into_iter().for_each(|(rank, k, v)| {
+        assert_eq!(rank, k);
+        assert_eq!(gen_sample(rank), v);
+    });
+}
+
+#[test]
+fn t_peek_first_last() {
+    crate::clear();
+
+    let mut db0 = crate::new_mapx!();
+    let mut db1 = crate::new_mapx!();
+    assert!(db0.peek_first().is_none());
+    assert!(db0.peek_last().is_none());
+
+    (0..1000usize).for_each(|i| {
+        db0.set_value(i, gen_sample(i));
+        db1.set_value(i, gen_sample(i));
+    });
+
+    // by the `Ord` of `K`, the same as `take_first_n` and `take_last_n`
+    assert_eq!(Some((0, gen_sample(0))), db0.peek_first());
+    assert_eq!(Some((999, gen_sample(999))), db0.peek_last());
+    assert_eq!(1000, db0.len());
+
+    db0.remove(&999);
+    assert_eq!(Some((998, gen_sample(998))), db0.peek_last());
+    assert_eq!(Some((999, gen_sample(999))), db1.peek_last());
+
+    let first = db1.peek_first();
+    assert_eq!(first, db1.take_first_n(1).pop());
+    let last = db1.peek_last();
+    assert_eq!(last, db1.take_last_n(1).pop());
+    assert_eq!(Some((1, gen_sample(1))), db1.peek_first());
+    assert_eq!(Some((998, gen_sample(998))), db1.peek_last());
+}
+
+#[test]
+fn t_atomic_swap_all() {
+    crate::clear();
+
+    let mut db0 = crate::new_mapx!();
+    let mut db1 = crate::new_mapx!();
+    (0..100usize).for_each(|i| {
+        db0.set_value(i, gen_sample(i));
+        db1.set_value(i, gen_sample(i));
+    });
+
+    pnk!(db0.atomic_swap_all((50..100usize).map(|i| (i * 10, gen_sample(i)))));
+    assert_eq!(50, db0.len());
+    (0..100usize)
+        .filter(|i| 0 != i % 10 || *i < 50)
+        .for_each(|i| assert!(!db0.contains_key(&i)));
+    (50..100usize).for_each(|i| assert_eq!(Some(gen_sample(i)), db0.get(&(i * 10))));
+
+    assert_eq!(100, db1.len());
+    assert_eq!(Some(gen_sample(99)), db1.get(&99));
+
+    pnk!(db0.atomic_swap_all(vec![(1, gen_sample(1)), (1, gen_sample(2))]));
+    assert_eq!(1, db0.len());
+    assert_eq!(Some(gen_sample(2)), db0.get(&1));
+}
+
+#[test]
+fn t_get_three() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..10usize).for_each(|i| db.set_value(i, gen_sample(i)));
+
+    assert_eq!(
+        (
+            Some(gen_sample(0)),
+            Some(gen_sample(5)),
+            Some(gen_sample(9))
+        ),
+        db.get_three(&0, &5, &9)
+    );
+    assert_eq!(
+        (Some(gen_sample(1)), None, Some(gen_sample(1))),
+        db.get_three(&1, &10, &1)
+    );
+    assert_eq!((None, None, None), db.get_three(&10, &11, &12));
+}
+
+#[test]
+fn t_scan_prefix_bytes() {
+    crate::clear();
+
+    #[derive(
+        Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+    )]
+    enum TypedKey {
+        A(u32),
+        B(u32),
+    }
+
+    let mut db = crate::new_mapx!();
+    (0..100u32).for_each(|i| {
+        db.insert(TypedKey::A(i), i);
+        db.insert(TypedKey::B(i), i);
+    });
+
+    // bincode uses a `u32` as the tag of enum variants
+    let b_tag = pnk!(bincode::serialize(&1u32));
+    let res = db.scan_prefix_bytes(&b_tag).collect::<Vec<_>>();
+    assert_eq!(100, res.len());
+    res.iter()
+        .for_each(|(k, v)| assert_eq!(&TypedKey::B(*v), k));
+
+    assert_eq!(200, db.scan_prefix_bytes(&[]).count());
+    assert_eq!(0, db.scan_prefix_bytes(&[9]).count());
+}
+
+#[test]
+fn t_try_from() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let data = (0..100usize)
+        .map(|i| (i, gen_sample(i)))
+        .collect::<BTreeMap<_, _>>();
+
+    let db: Mapx<usize, SampleBlock> = pnk!((data.clone(), path.as_str()).try_into());
+    assert_eq!(100, db.len());
+    assert_eq!(path, db.get_path());
+
+    let restored: BTreeMap<usize, SampleBlock> = pnk!(db.try_into());
+    assert_eq!(data, restored);
+}
+
+#[test]
+fn t_into_sorted_vec() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert!(db.to_sorted_vec().is_empty());
+
+    // little-endian keys beyond `u8::MAX` are not in numeric order in the storage
+    (0..1000usize).rev().for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+
+    let expected = (0..1000).map(|i| (i, gen_sample(i))).collect::<Vec<_>>();
+    assert_eq!(expected, db.to_sorted_vec());
+    assert_eq!(1000, db.len());
+
+    assert_eq!(expected, db.clone().into_sorted_vec());
+    assert_eq!((0..1000).collect::<Vec<_>>(), db.clone().into_keys());
+    assert_eq!(
+        (0..1000).map(gen_sample).collect::<Vec<_>>(),
+        db.into_values()
+    );
+}
+
+#[test]
+fn t_metrics() {
+    crate::clear();
+
+    let db: Mapx<usize, SampleBlock> = crate::new_mapx!();
+    assert!(db.get(&0).is_none());
+    assert!(db.metrics().is_none());
+
+    let mut db = pnk!(Mapx::with_metrics(&crate::unique_path!()));
+    (0..100usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+    (0..50usize).for_each(|i| {
+        assert_eq!(i * 3 < 100, db.get(&(i * 3)).is_some());
+    });
+    db.remove(&0);
+    assert!(db.contains_key(&1));
+    assert_eq!(99, db.iter().count());
+
+    let m = pnk!(db.metrics());
+    assert_eq!(100, m.insert_count.load(AtomicOrdering::Relaxed));
+    assert_eq!(1, m.remove_count.load(AtomicOrdering::Relaxed));
+    assert_eq!(34, m.get_hit_count.load(AtomicOrdering::Relaxed));
+    assert_eq!(16, m.get_miss_count.load(AtomicOrdering::Relaxed));
+    assert_eq!(1, m.contains_key_count.load(AtomicOrdering::Relaxed));
+    assert_eq!(1, m.iter_count.load(AtomicOrdering::Relaxed));
+}
+
+#[test]
+fn t_move_entry() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert!(db.move_entry(&"a".to_owned(), "b".to_owned()).is_none());
+
+    db.insert("a".to_owned(), gen_sample(0));
+    db.insert("c".to_owned(), gen_sample(2));
+
+    assert_eq!(
+        Some(gen_sample(0)),
+        db.move_entry(&"a".to_owned(), "b".to_owned())
+    );
+    assert!(!db.contains_key(&"a".to_owned()));
+    assert_eq!(Some(gen_sample(0)), db.get(&"b".to_owned()));
+    assert_eq!(2, db.len());
+
+    // overwrite an existing key
+    assert_eq!(
+        Some(gen_sample(0)),
+        db.move_entry(&"b".to_owned(), "c".to_owned())
+    );
+    assert_eq!(Some(gen_sample(0)), db.get(&"c".to_owned()));
+    assert_eq!(1, db.len());
+
+    assert_eq!(
+        Some(gen_sample(0)),
+        db.move_entry(&"c".to_owned(), "c".to_owned())
+    );
+    assert_eq!(1, db.len());
+}
+
+#[test]
+fn t_eviction_policy() {
+    crate::clear();
+
+    let cold_path = crate::unique_path!();
+    let mut db = crate::new_mapx!();
+    db.set_eviction_policy(EvictionPolicy {
+        cold_store: pnk!(Mapx::new(&cold_path)),
+        access_threshold: 20,
+    });
+
+    (0..10usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+    (10..100usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+        (0..10usize).for_each(|j| assert_eq!(Some(gen_sample(j)), db.get(&j)));
+    });
+    // write the hot keys again, so all the cold keys reach the threshold
+    (0..20usize).for_each(|i| db.set_value(i % 10, gen_sample(i % 10)));
+
+    assert_eq!(10, db.len());
+    (0..100usize).for_each(|i| {
+        assert_eq!(Some(gen_sample(i)), db.get(&i));
+        assert!(db.contains_key(&i));
+    });
+
+    let primary: Mapx<usize, SampleBlock> = pnk!(Mapx::new(db.get_path()));
+    let cold: Mapx<usize, SampleBlock> = pnk!(Mapx::new(&cold_path));
+    assert_eq!(10, primary.len());
+    assert_eq!(90, cold.len());
+    (0..10usize).for_each(|i| assert!(primary.contains_key(&i)));
+    (10..100usize).for_each(|i| assert!(cold.contains_key(&i)));
+
+    // a cold key is moved back on writing
+    assert_eq!(Some(gen_sample(50)), db.insert(50, gen_sample(500)));
+    assert_eq!(11, db.len());
+    assert_eq!(Some(gen_sample(500)), db.get(&50));
+
+    assert_eq!(Some(gen_sample(60)), db.remove(&60));
+    assert!(db.get(&60).is_none());
+    assert_eq!(11, db.len());
+
+    // 70 is cold, 0 is hot, 60 has been removed
+    let mut other = crate::new_mapx!();
+    [70, 0, 60].iter().for_each(|i| {
+        other.insert(*i, ());
+    });
+    assert_eq!(2, db.subtract_keys(&other));
+    assert!(db.get(&70).is_none());
+    assert!(db.get(&0).is_none());
+    assert!(!db.contains_key(&70));
+    assert_eq!(10, db.len());
+    assert!(!cold.contains_key(&70));
+
+    pnk!(db.atomic_swap_all(vec![(1, gen_sample(1))]));
+    assert!(db.get(&80).is_none());
+    let cold: Mapx<usize, SampleBlock> = pnk!(Mapx::new(&cold_path));
+    assert!(cold.is_empty());
+    assert_eq!(Some(gen_sample(1)), db.get(&1));
+}
+
+#[test]
+fn t_eviction_policy_batch() {
+    crate::clear();
+
+    let cold_path = crate::unique_path!();
+    let mut db = crate::new_mapx!();
+    db.set_eviction_policy(EvictionPolicy {
+        cold_store: pnk!(Mapx::new(&cold_path)),
+        access_threshold: 20,
+    });
+
+    // the batch writes are tracked, so the early keys are evicted
+    let mut other = crate::new_mapx!();
+    (0..100usize).for_each(|i| {
+        other.insert(i, gen_sample(i));
+    });
+    db.union_with(&other, |_, _, v| v.clone());
+    assert_eq!(20, db.len());
+    let cold: Mapx<usize, SampleBlock> = pnk!(Mapx::new(&cold_path));
+    assert_eq!(80, cold.len());
+    (0..100usize).for_each(|i| assert_eq!(Some(gen_sample(i)), db.get(&i)));
+
+    // a cold key can be moved
+    assert_eq!(Some(gen_sample(0)), db.move_entry(&0, 1000));
+    assert!(db.get(&0).is_none());
+    assert!(!cold.contains_key(&0));
+    assert_eq!(Some(gen_sample(0)), db.get(&1000));
+    assert_eq!(21, db.len());
+
+    // removing a cold key in a patch leaves no stale copy
+    pnk!(db.apply_patch(vec![
+        PatchEntry::Remove(1),
+        PatchEntry::Insert(2, gen_sample(200)),
+    ]));
+    assert!(db.get(&1).is_none());
+    assert!(!cold.contains_key(&1));
+    assert!(!cold.contains_key(&2));
+    assert_eq!(Some(gen_sample(200)), db.get(&2));
+
+    // the removed keys are no longer tracked
+    db.retain_keys(|k| *k != 1000);
+    pnk!(db.update_batch(|k, v| alt!(2 == *k, None, Some(v))));
+    assert!(db.get(&1000).is_none());
+    assert!(db.get(&2).is_none());
+    assert!(!cold.contains_key(&1000));
+    assert!(!cold.contains_key(&2));
+
+    // every remaining key is kept in exactly one of the stores
+    let primary: Mapx<usize, SampleBlock> = pnk!(Mapx::new(db.get_path()));
+    assert_eq!(db.len(), primary.len());
+    (3..100usize).for_each(|i| {
+        assert_eq!(Some(gen_sample(i)), db.get(&i));
+        assert_ne!(primary.contains_key(&i), cold.contains_key(&i));
+    });
+}
+
+#[test]
+fn t_warm_up() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..1000usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+
+    db.warm_up(&(0..100).collect::<Vec<_>>());
+    db.warm_up(&[]);
+    db.warm_up_range(200..300);
+    db.warm_up_range(..);
+
+    (0..1000usize).for_each(|i| assert_eq!(Some(gen_sample(i)), db.get(&i)));
+    assert_eq!(1000, db.iter().count());
+}
+
+#[test]
+fn t_iter_batched() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert_eq!(0, db.iter_batched(10).count());
+
+    (0..100usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+
+    let batches = db.iter_batched(10).collect::<Vec<_>>();
+    assert_eq!(10, batches.len());
+    assert!(batches.iter().all(|b| 10 == b.len()));
+
+    let batches = db.iter_batched(15).collect::<Vec<_>>();
+    assert_eq!(7, batches.len());
+    assert!(batches[..6].iter().all(|b| 15 == b.len()));
+    assert_eq!(10, batches[6].len());
+
+    assert_eq!(
+        db.iter().collect::<Vec<_>>(),
+        batches.into_iter().flatten().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn t_apply_patch() {
+    crate::clear();
+
+    let mut old = crate::new_mapx!();
+    let mut new = crate::new_mapx!();
+    (0..100usize).for_each(|i| {
+        old.insert(i, gen_sample(i));
+        if 0 != i % 3 {
+            new.insert(i, gen_sample(alt!(0 == i % 2, i, 1000 + i)));
+        }
+    });
+    (100..120usize).for_each(|i| {
+        new.insert(i, gen_sample(i));
+    });
+
+    let mut patch = old
+        .iter()
+        .filter(|(k, _)| !new.contains_key(k))
+        .map(|(k, _)| PatchEntry::Remove(k))
+        .collect::<Vec<_>>();
+    new.iter()
+        .filter(|(k, v)| old.get(k).as_ref() != Some(v))
+        .for_each(|(k, v)| patch.push(PatchEntry::Insert(k, v)));
+
+    let patch = pnk!(serde_json::to_vec(&patch));
+    let patch: Vec<PatchEntry<usize, SampleBlock>> =
+        pnk!(serde_json::from_slice(&patch));
+
+    let mut copy = crate::new_mapx!();
+    old.iter().for_each(|(k, v)| {
+        copy.insert(k, v);
+    });
+    pnk!(copy.apply_patch(patch));
+
+    assert_eq!(new.len(), copy.len());
+    assert_eq!(new.to_sorted_vec(), copy.to_sorted_vec());
+
+    // the operations on the same key take effect in order
+    pnk!(copy.apply_patch(vec![
+        PatchEntry::Remove(1),
+        PatchEntry::Insert(1, gen_sample(1)),
+        PatchEntry::Insert(1000, gen_sample(1000)),
+        PatchEntry::Remove(1000),
+    ]));
+    assert_eq!(Some(gen_sample(1)), copy.get(&1));
+    assert!(copy.get(&1000).is_none());
+    assert_eq!(new.len(), copy.len());
+}
+
+#[test]
+fn t_tap() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+
+    let mut seen = None;
+    assert_eq!(
+        Some(gen_sample(1)),
+        db.tap_get(&1, |v| seen = v.map(|v| v.idx))
+    );
+    assert_eq!(Some(1), seen);
+    assert!(db.tap_get(&1000, |v| assert!(v.is_none())).is_none());
+
+    let mut keys = vec![];
+    let it = db.tap_iter(|k, v| {
+        assert_eq!(*k, v.idx);
+        keys.push(*k);
+    });
+    assert_eq!(100, it.len());
+    assert_eq!(db.iter().collect::<Vec<_>>(), it.collect::<Vec<_>>());
+    assert_eq!(db.iter().map(|(k, _)| k).collect::<Vec<_>>(), keys);
+}
+
+#[test]
+fn t_namespace_prefix() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let mut db_a = pnk!(Mapx::with_namespace_prefix(&path, b"a/".to_vec()));
+    let mut db_b = pnk!(Mapx::with_namespace_prefix(&path, b"b/".to_vec()));
+
+    (0..100usize).for_each(|i| {
+        db_a.insert(i, gen_sample(i));
+    });
+    (50..200usize).for_each(|i| {
+        db_b.insert(i, gen_sample(1000 + i));
+    });
+
+    assert_eq!(100, db_a.len());
+    assert_eq!(150, db_b.len());
+    assert_eq!(Some(gen_sample(60)), db_a.get(&60));
+    assert_eq!(Some(gen_sample(1060)), db_b.get(&60));
+    assert!(db_a.get(&150).is_none());
+    assert!(db_b.get(&10).is_none());
+    assert!(db_a.iter().all(|(k, v)| k == v.idx));
+    assert!(db_b.iter().all(|(k, v)| 1000 + k == v.idx));
+
+    db_a.remove(&60);
+    assert!(db_a.get(&60).is_none());
+    assert_eq!(Some(gen_sample(1060)), db_b.get(&60));
+
+    let db_a: Mapx<usize, SampleBlock> =
+        pnk!(Mapx::with_namespace_prefix(&path, b"a/".to_vec()));
+    assert_eq!(99, db_a.len());
+    assert!(db_a.clone().rename(&crate::unique_path!()).is_err());
+
+    // the meta is kept for each namespace
+    pnk!(db_a.assert_schema());
+    let db_c: Mapx<String, String> =
+        pnk!(Mapx::with_namespace_prefix(&path, b"c/".to_vec()));
+    pnk!(db_c.assert_schema());
+    pnk!(db_a.assert_schema());
+
+    assert!(Mapx::<usize, usize>::with_namespace_prefix(&path, vec![]).is_err());
+
+    // the plain instance of the path sees none of the namespaced keys
+    let mut db: Mapx<usize, SampleBlock> = pnk!(Mapx::new(&path));
+    assert_eq!(0, db.len());
+    assert!(db.iter().next().is_none());
+    db.insert(1, gen_sample(2000));
+    assert_eq!(1, db.len());
+    assert_eq!(Some(gen_sample(1)), db_a.get(&1));
+    assert_eq!(99, db_a.len());
+    assert_eq!(150, db_b.len());
+
+    // no namespace is a prefix of another one
+    let mut db_x: Mapx<u8, u8> = pnk!(Mapx::with_namespace_prefix(&path, b"x".to_vec()));
+    let mut db_xy: Mapx<u8, u8> =
+        pnk!(Mapx::with_namespace_prefix(&path, b"xy".to_vec()));
+    db_x.insert(b'y', 1);
+    db_xy.insert(b'y', 2);
+    assert_eq!(1, db_x.len());
+    assert_eq!(1, db_xy.len());
+    assert_eq!(Some(1), db_x.get(&b'y'));
+    assert_eq!(Some(2), db_xy.get(&b'y'));
+}
+
+#[cfg(not(feature = "serialize-full"))]
+#[test]
+fn t_namespace_prefix_serde() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let mut db_a = pnk!(Mapx::with_namespace_prefix(&path, b"a/".to_vec()));
+    let mut db_b = pnk!(Mapx::with_namespace_prefix(&path, b"b/".to_vec()));
+    db_a.insert(1usize, 10usize);
+    db_b.insert(1usize, 20usize);
+    db_b.insert(2usize, 30usize);
+
+    let json = pnk!(serde_json::to_string(&db_a));
+    let db_a = pnk!(serde_json::from_str::<Mapx<usize, usize>>(&json));
+    assert_eq!(1, db_a.len());
+    assert_eq!(Some(10), db_a.get(&1));
+
+    let json = pnk!(serde_json::to_string(&db_b));
+    let db_b = pnk!(serde_json::from_str::<Mapx<usize, usize>>(&json));
+    assert_eq!(2, db_b.len());
+    assert_eq!(Some(20), db_b.get(&1));
+}
+
+#[test]
+fn t_count_keys_in_range_exact() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert_eq!(0, db.count_keys_in_range_exact(..));
+
+    pnk!(db.atomic_swap_all((0..10_000usize).map(|i| (i, gen_sample(i)))));
+
+    assert_eq!(10_000, db.count_keys_in_range_exact(..));
+    assert_eq!(1000, db.count_keys_in_range_exact(1000..2000));
+    assert_eq!(1001, db.count_keys_in_range_exact(1000..=2000));
+    assert_eq!(500, db.count_keys_in_range_exact(9500..));
+    assert_eq!(300, db.count_keys_in_range_exact(..300));
+    assert_eq!(0, db.count_keys_in_range_exact(20_000..));
+}
+
+#[test]
+fn t_scan_with_limit() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    let (page, cursor) = db.scan_with_limit(None, 10);
+    assert!(page.is_empty());
+    assert!(cursor.is_none());
+
+    (0..100usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+
+    let mut all = vec![];
+    let mut cursor = None;
+    for _ in 0..10 {
+        let (page, next) = db.scan_with_limit(cursor.as_ref(), 10);
+        assert_eq!(10, page.len());
+        assert!(next.is_some());
+        all.extend(page);
+        cursor = next;
+    }
+    let (page, next) = db.scan_with_limit(cursor.as_ref(), 10);
+    assert!(page.is_empty());
+    assert!(next.is_none());
+    assert_eq!(db.iter().collect::<Vec<_>>(), all);
+
+    let (page, next) = db.scan_with_limit(None, 15);
+    let (page2, _) = db.scan_with_limit(next.as_ref(), 15);
+    assert_eq!(
+        db.iter().take(30).collect::<Vec<_>>(),
+        [page, page2].concat()
+    );
+
+    // a missing key can also be the start
+    db.remove(&50);
+    let (page, _) = db.scan_with_limit(Some(&50), 1000);
+    assert_eq!(db.iter().filter(|(k, _)| *k > 50).count(), page.len());
+}
+
+#[test]
+fn t_keys_after_before() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert!(db.keys_after(&0, 5).is_empty());
+
+    (0..1000usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+    db.remove(&53);
+
+    assert_eq!(vec![51, 52, 54, 55, 56], db.keys_after(&50, 5));
+    assert_eq!(vec![49, 48, 47, 46, 45], db.keys_before(&50, 5));
+    assert_eq!(vec![52, 51, 50], db.keys_before(&53, 3));
+
+    // beyond `u8::MAX`, the storage order differs from the numeric order
+    assert_eq!(vec![256, 257], db.keys_after(&255, 2));
+    assert_eq!(vec![256, 255], db.keys_before(&257, 2));
+
+    assert_eq!(vec![998, 999], db.keys_after(&997, 5));
+    assert_eq!(vec![1, 0], db.keys_before(&2, 5));
+    assert!(db.keys_after(&999, 5).is_empty());
+    assert!(db.keys_after(&50, 0).is_empty());
+}
+
+#[test]
+fn t_snapshot_to_vecx() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..50usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+
+    let values = pnk!(db.snapshot_values_to_vecx(&crate::unique_path!()));
+    assert_eq!(50, values.len());
+    assert_eq!(db.iter().nth(25).map(|(_, v)| v), values.get(25));
+
+    let keys = pnk!(db.snapshot_keys_to_vecx(&crate::unique_path!()));
+    assert_eq!(50, keys.len());
+    assert_eq!(
+        db.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+        keys.iter().collect::<Vec<_>>()
+    );
+
+    // sorted by `Ord`, not by the bincode bytes
+    let mut db = crate::new_mapx!();
+    [1u32, 256, 2].iter().for_each(|i| {
+        db.insert(*i, *i * 10);
+    });
+    let keys = pnk!(db.snapshot_keys_to_vecx(&crate::unique_path!()));
+    assert_eq!(
+        vec![Some(1), Some(2), Some(256)],
+        (0..3).map(|i| keys.get(i)).collect::<Vec<_>>()
+    );
+    let values = pnk!(db.snapshot_values_to_vecx(&crate::unique_path!()));
+    assert_eq!(
+        vec![Some(10), Some(20), Some(2560)],
+        (0..3).map(|i| values.get(i)).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn t_union_with() {
+    crate::clear();
+
+    let mut a = crate::new_mapx!();
+    let mut b = crate::new_mapx!();
+    (0..100usize).for_each(|i| {
+        a.insert(i, i);
+    });
+    (50..150usize).for_each(|i| {
+        b.insert(i, 200 - i);
+    });
+
+    a.union_with(&b, |_, x, y| *x.max(y));
+    assert_eq!(150, a.len());
+    assert_eq!(100, b.len());
+    (0..150usize).for_each(|i| {
+        let expected = match i {
+            0..=49 => i,
+            50..=99 => i.max(200 - i),
+            _ => 200 - i,
+        };
+        assert_eq!(Some(expected), a.get(&i));
+    });
+}
+
+#[test]
+fn t_subtract_keys() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    let mut other = crate::new_mapx!();
+    (0..100usize).for_each(|i| {
+        db.insert(i, gen_sample(i));
+    });
+    (70..120usize).for_each(|i| {
+        other.insert(i, format!("{}", i));
+    });
+
+    assert_eq!(30, db.subtract_keys(&other));
+    assert_eq!(70, db.len());
+    assert!((0..70usize).all(|i| db.contains_key(&i)));
+    assert!((70..120usize).all(|i| !db.contains_key(&i)));
+
+    assert_eq!(0, db.subtract_keys(&other));
+    assert_eq!(70, db.len());
+}
+
+#[test]
+fn t_add() {
+    crate::clear();
+
+    let mut a = crate::new_mapx!();
+    let mut b = crate::new_mapx!();
+    (0..100usize).for_each(|i| {
+        a.insert(i, i);
+    });
+    (50..150usize).for_each(|i| {
+        b.insert(i, 1000 + i);
+    });
+
+    let c = &a + &b;
+    assert_eq!(150, c.len());
+    assert_eq!(100, a.len());
+    assert_eq!(100, b.len());
+    assert_eq!(Some(10), c.get(&10));
+    assert_eq!(Some(1060), c.get(&60));
+    assert_ne!(a.get_path(), c.get_path());
+    assert_ne!(b.get_path(), c.get_path());
+
+    a += &b;
+    assert_eq!(150, a.len());
+    assert_eq!(c, a);
+
+    let d = a.clone() + crate::new_mapx!();
+    assert_eq!(c, d);
+}
+
+#[test]
+fn t_flip_key_value() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..50usize).for_each(|i| {
+        db.insert(i, format!("v{}", i));
+    });
+
+    let flipped = pnk!(db.flip_key_value(&crate::unique_path!()));
+    assert_eq!(50, flipped.len());
+    assert_eq!(Some(7), flipped.get(&"v7".to_owned()));
+
+    let restored = pnk!(flipped.flip_key_value(&crate::unique_path!()));
+    assert_eq!(db.to_sorted_vec(), restored.to_sorted_vec());
+
+    let path = db.get_path().to_owned();
+    let flipped = pnk!(db.clone().flip_key_value_in_place());
+    assert_eq!(path, flipped.get_path());
+    assert_eq!(50, flipped.len());
+    let restored = pnk!(flipped.flip_key_value_in_place());
+    assert_eq!(path, restored.get_path());
+    assert_eq!(
+        (0..50).map(|i| (i, format!("v{}", i))).collect::<Vec<_>>(),
+        restored.to_sorted_vec()
+    );
+
+    // not injective, the last one in storage order wins
+    let mut db = crate::new_mapx!();
+    (0..10usize).for_each(|i| {
+        db.insert(i, i / 5);
+    });
+    let flipped = pnk!(db.flip_key_value_in_place());
+    assert_eq!(vec![(0, 4), (1, 9)], flipped.to_sorted_vec());
+}
+
+#[test]
+fn t_pipe_to_load_from() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..1000usize).for_each(|i| {
+        db.insert(format!("\"k\",\n{}", i), gen_sample(i));
+    });
+
+    for format in [
+        ExportFormat::NdJson,
+        ExportFormat::Csv,
+        ExportFormat::BinaryCoded,
+    ] {
+        let mut buf = vec![];
+        assert_eq!(1000, pnk!(db.pipe_to(&mut buf, format)));
+
+        let restored: Mapx<String, SampleBlock> = pnk!(Mapx::load_from(
+            &mut &buf[..],
+            format,
+            &crate::unique_path!()
+        ));
+        assert_eq!(1000, restored.len());
+        assert_eq!(db.to_sorted_vec(), restored.to_sorted_vec());
+
+        let empty: Mapx<String, SampleBlock> = crate::new_mapx!();
+        let mut buf = vec![];
+        assert_eq!(0, pnk!(empty.pipe_to(&mut buf, format)));
+        let restored: Mapx<String, SampleBlock> = pnk!(Mapx::load_from(
+            &mut &buf[..],
+            format,
+            &crate::unique_path!()
+        ));
+        assert!(restored.is_empty());
+    }
+
+    let bad = b"key,value\n\"1\"\n";
+    assert!(Mapx::<usize, usize>::load_from(
+        &mut &bad[..],
+        ExportFormat::Csv,
+        &crate::unique_path!()
+    )
+    .is_err());
+
+    // a truncated entry
+    let mut buf = vec![];
+    pnk!(db.pipe_to(&mut buf, ExportFormat::BinaryCoded));
+    buf.truncate(buf.len() - 1);
+    assert!(Mapx::<String, SampleBlock>::load_from(
+        &mut &buf[..],
+        ExportFormat::BinaryCoded,
+        &crate::unique_path!()
+    )
+    .is_err());
+
+    // a truncated length header after the complete entries
+    let mut buf = vec![];
+    pnk!(db.pipe_to(&mut buf, ExportFormat::BinaryCoded));
+    buf.extend_from_slice(&[1, 0, 0]);
+    assert!(Mapx::<String, SampleBlock>::load_from(
+        &mut &buf[..],
+        ExportFormat::BinaryCoded,
+        &crate::unique_path!()
+    )
+    .is_err());
+
+    // an oversized length header
+    let bad = u64::MAX.to_le_bytes();
+    assert!(Mapx::<usize, usize>::load_from(
+        &mut &bad[..],
+        ExportFormat::BinaryCoded,
+        &crate::unique_path!()
+    )
+    .is_err());
+}
+
+#[test]
+fn t_update_in_place() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    db.insert(1, "abc".to_owned());
+
+    assert!(pnk!(db.update_in_place(&1, |v| v.push_str("-suffix"))));
+    assert_eq!(Some("abc-suffix".to_owned()), db.get(&1));
+    assert_eq!(1, db.len());
+
+    assert!(!pnk!(db.update_in_place(&2, |_| panic!("not exist"))));
+    assert!(db.get(&2).is_none());
+    assert_eq!(1, db.len());
+}
+
+#[test]
+fn t_contains_all_any() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..50usize).for_each(|i| db.set_value(i, i));
+
+    assert!(db.contains_all(&[10, 20, 30]));
+    assert!(!db.contains_all(&[10, 60]));
+    assert!(db.contains_all(&[]));
+
+    assert!(!db.contains_any(&[60, 70, 80]));
+    assert!(db.contains_any(&[60, 10]));
+    assert!(!db.contains_any(&[]));
+}
+
+#[test]
+fn t_get_with_default() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    {
+        let mut v = db.get_with_default("counter".to_owned(), 0);
+        assert_eq!(0, *v);
+        *v = 5;
+    }
+    assert_eq!(Some(5), db.get(&"counter".to_owned()));
+
+    // an existing value is not overwritten
+    assert_eq!(5, *db.get_with_default("counter".to_owned(), 0));
+    assert_eq!(1, db.len());
+}
+
+#[test]
+fn t_retain_keys_values() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DECODED: AtomicUsize = AtomicUsize::new(0);
+
+    // counts how many times a value is deserialized
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    struct Counted(usize);
+
+    impl<'de> Deserialize<'de> for Counted {
+        fn deserialize<D: serde::Deserializer<'de>>(
+            d: D,
+        ) -> std::result::Result<Self, D::Error> {
+            DECODED.fetch_add(1, Ordering::Relaxed);
+            usize::deserialize(d).map(Counted)
+        }
+    }
+
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100usize).for_each(|i| db.set_value("x".repeat(i % 10), Counted(i)));
+    assert_eq!(10, db.len());
+
+    db.retain_keys(|k| k.len() > 5);
+    assert_eq!(0, DECODED.load(Ordering::Relaxed));
+    assert_eq!(4, db.len());
+    assert!(db.iter().all(|(k, _)| k.len() > 5));
+
+    db.retain_values(|v| 0 == v.0 % 2);
+    assert_eq!(2, db.len());
+    assert_eq!(Some(Counted(96)), db.get(&"x".repeat(6)));
+    assert_eq!(Some(Counted(98)), db.get(&"x".repeat(8)));
+}
+
+#[test]
+fn t_get_with_timestamp_hint() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert!(db.get_with_timestamp_hint(&1).is_none());
+    assert!(db.get_all_versions(&1).is_empty());
+
+    // the modification time of the newest SST file covering the key
+    let ((idx, mut k), _) = db.in_disk.counter();
+    k.append(&mut pnk!(bincode::serialize(&1)));
+    let sst_mtime = || {
+        pnk!(crate::helper::BNC[idx].live_files())
+            .into_iter()
+            .filter(|f| {
+                f.start_key.as_deref().map(|s| s <= &k[..]).unwrap_or(false)
+                    && f.end_key.as_deref().map(|e| &k[..] <= e).unwrap_or(false)
+            })
+            .filter_map(|f| {
+                fs::metadata(format!("{}{}", crate::BNC_DATA_LIST[idx], f.name))
+                    .and_then(|m| m.modified())
+                    .ok()
+            })
+            .max()
+    };
+
+    db.insert(1, gen_sample(1));
+    pnk!(db.flush());
+    let first = pnk!(sst_mtime());
+    std::thread::sleep(Duration::from_millis(20));
+
+    db.insert(1, gen_sample(2));
+    pnk!(db.flush());
+    let second = pnk!(sst_mtime());
+    assert!(second > first);
+
+    // the key has been flushed, so the hint is the time of the SST file
+    let (v, hint) = pnk!(db.get_with_timestamp_hint(&1));
+    assert_eq!(gen_sample(2), v);
+    assert_eq!(second, hint);
+
+    let versions = db.get_all_versions(&1);
+    assert_eq!(1, versions.len());
+    assert_eq!(gen_sample(2), versions[0].value);
+    assert_eq!(second, versions[0].timestamp);
+}
+
+#[test]
+fn t_open_with_version() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let no_migration = |_: &mut Mapx<usize, usize>, _: u32| -> Result<()> {
+        panic!("should not be called")
+    };
+
+    {
+        let mut db = pnk!(Mapx::open_with_version(&path, 1, no_migration));
+        (0..10).for_each(|i| db.set_value(i, i));
+    }
+
+    let migrated = std::cell::Cell::new(None);
+    let db: Mapx<usize, usize> = pnk!(Mapx::open_with_version(&path, 2, |db, from| {
+        migrated.set(Some(from));
+        db.update_batch(|_, v| Some(v * 2)).c(d!())
+    }));
+    assert_eq!(Some(1), migrated.get());
+    (0..10).for_each(|i| assert_eq!(Some(i * 2), db.get(&i)));
+
+    // the same version, no migration
+    let db = pnk!(Mapx::open_with_version(&path, 2, no_migration));
+    assert_eq!(Some(18), db.get(&9));
+
+    // downgrade is not allowed
+    assert!(Mapx::open_with_version(&path, 1, no_migration).is_err());
+}
+
+#[test]
+fn t_assert_schema() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    {
+        let db: Mapx<u32, String> = pnk!(Mapx::new(&path));
+        pnk!(db.assert_schema());
+        pnk!(db.assert_schema());
+    }
+
+    let db: Mapx<u32, String> = pnk!(Mapx::new(&path));
+    pnk!(db.assert_schema());
+
+    let db: Mapx<u64, String> = pnk!(Mapx::new(&path));
+    assert!(db.assert_schema().is_err());
+}
+
+#[test]
+fn t_build_secondary_index() {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct User {
+        name: String,
+        email: String,
+    }
+
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100usize).for_each(|i| {
+        db.set_value(
+            i,
+            User {
+                name: format!("user{}", i),
+                email: format!("user{}@example.com", i),
+            },
+        )
+    });
+
+    let index =
+        pnk!(db.build_secondary_index(&crate::unique_path!(), |u| u.email.clone()));
+    assert_eq!(100, index.len());
+
+    let id = pnk!(index.get(&"user42@example.com".to_owned()));
+    assert_eq!(42, id);
+    assert_eq!("user42", pnk!(db.get(&id)).name);
+    assert!(index.get(&"nobody@example.com".to_owned()).is_none());
+
+    // not maintained automatically
+    db.unset_value(&42);
+    assert!(index.contains_key(&"user42@example.com".to_owned()));
+}
+
+#[test]
+fn t_for_each_concurrent() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..1000usize).for_each(|i| db.set_value(i, i));
+
+    let cnter = Arc::new(AtomicUsize::new(0));
+    let sum = Arc::new(AtomicUsize::new(0));
+    {
+        let cnter = Arc::clone(&cnter);
+        let sum = Arc::clone(&sum);
+        db.for_each_concurrent(8, move |k, v| {
+            assert_eq!(k, v);
+            cnter.fetch_add(1, Ordering::Relaxed);
+            sum.fetch_add(v, Ordering::Relaxed);
+        });
+    }
+    assert_eq!(1000, cnter.load(Ordering::Relaxed));
+    assert_eq!((0..1000).sum::<usize>(), sum.load(Ordering::Relaxed));
+}
+
+#[test]
+fn t_builder() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let log_path = format!("/tmp/bnc_test/{}.log", path.replace('/', "_"));
+    omit!(fs::create_dir_all("/tmp/bnc_test"));
+    omit!(fs::remove_file(&log_path));
+
+    assert!(Mapx::<usize, usize>::builder()
+        .path(&path)
+        .create_if_missing(false)
+        .build()
+        .is_err());
+
+    let mut db = pnk!(Mapx::builder()
+        .path(&path)
+        .create_if_missing(true)
+        .namespace_prefix(b"ns".to_vec())
+        .metrics(true)
+        .change_log(&log_path)
+        .build());
+    assert_eq!(path, db.get_path());
+    (0..10usize).for_each(|i| db.set_value(i, i));
+    assert_eq!(10, db.len());
+    assert_eq!(Some(5), db.get(&5));
+    assert_eq!(
+        10,
+        pnk!(db.metrics())
+            .insert_count
+            .load(AtomicOrdering::Relaxed)
+    );
+    pnk!(db.flush());
+    assert_eq!(10, pnk!(fs::read_to_string(&log_path)).lines().count());
+
+    // the instance exists now
+    let db = pnk!(MapxBuilder::<usize, usize>::default()
+        .path(&path)
+        .create_if_missing(false)
+        .namespace_prefix(b"ns".to_vec())
+        .build());
+    assert_eq!(10, db.len());
+
+    let db = pnk!(MapxBuilder::<usize, usize>::default().build());
+    assert!(db.is_empty());
+}
+
+#[test]
+fn t_run_length_encode() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    let values = ["a", "a", "b", "b", "b", "a", "c", "c"];
+    values
+        .iter()
+        .enumerate()
+        .for_each(|(i, v)| db.set_value(i as u8, v.to_string()));
+
+    let encoded = pnk!(db.run_length_encode(&crate::unique_path!()));
+    assert_eq!(
+        vec![
+            (0, ("a".to_owned(), 2)),
+            (2, ("b".to_owned(), 3)),
+            (5, ("a".to_owned(), 1)),
+            (6, ("c".to_owned(), 2)),
+        ],
+        encoded.iter().collect::<Vec<_>>()
+    );
+
+    // the runs cross 256, the key order differs from the storage order
+    let mut db = crate::new_mapx!();
+    (250..262u32).for_each(|i| db.set_value(i, alt!(256 > i, "a", "b").to_owned()));
+    let encoded = pnk!(db.run_length_encode(&crate::unique_path!()));
+    assert_eq!(
+        vec![(250, ("a".to_owned(), 6)), (256, ("b".to_owned(), 6))],
+        encoded.to_sorted_vec()
+    );
+
+    let empty: Mapx<u8, String> = crate::new_mapx!();
+    assert!(pnk!(empty.run_length_encode(&crate::unique_path!())).is_empty());
+}
+
+#[test]
+fn t_from_iter_at_path() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let db = pnk!(Mapx::from_iter_at_path(
+        vec![
+            (1u32, "a".to_owned()),
+            (2, "b".to_owned()),
+            (1, "c".to_owned())
+        ],
+        &path
+    ));
+    assert_eq!(path, db.get_path());
+    assert_eq!(2, db.len());
+    assert_eq!(Some("c".to_owned()), db.get(&1));
+    assert_eq!(Some("b".to_owned()), db.get(&2));
+
+    let db: Mapx<u32, String> = pnk!(Mapx::new(&path));
+    assert_eq!(2, db.len());
+}
+
+#[test]
+fn t_estimate_memory_overhead() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..10_000usize).for_each(|i| db.set_value(i, gen_sample(i)));
+
+    // read the same numbers from the properties of rocksdb
+    let ((idx, _), _) = db.in_disk.counter();
+    let props = || {
+        [
+            "rocksdb.block-cache-usage",
+            "rocksdb.cur-size-all-mem-tables",
+            "rocksdb.size-all-mem-tables",
+            "rocksdb.estimate-table-readers-mem",
+        ]
+        .iter()
+        .map(|p| pnk!(crate::helper::BNC[idx].property_int_value(p)).unwrap_or(0))
+        .collect::<Vec<_>>()
+    };
+
+    // the other tests may write to the same db instance at the same time,
+    // so retry until the properties are stable during the estimation
+    for _ in 0..100 {
+        let before = props();
+        let m = db.estimate_memory_overhead();
+        if before != props() {
+            continue;
+        }
+
+        let expected = MemoryBreakdown {
+            block_cache_bytes: before[0],
+            write_buffer_bytes: before[1],
+            table_readers_bytes: before[3],
+            misc_bytes: before[2] - before[1],
+        };
+        assert_eq!(expected, m);
+        assert_eq!(before[0] + before[2] + before[3], m.total_bytes());
+        assert!(0 < m.total_bytes());
+        return;
+    }
+    panic!("the properties are always changing");
+}
+
+#[test]
+fn t_transform_keys() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100u64).for_each(|i| db.set_value(i, gen_sample(i as usize)));
+
+    let doubled = pnk!(db.transform_keys(&crate::unique_path!(), |k| k * 2));
+    assert_eq!(db.len(), doubled.len());
+    assert!(doubled.iter().all(|(k, _)| 0 == k % 2));
+    (0..100).for_each(|i| assert_eq!(db.get(&i), doubled.get(&(i * 2))));
+
+    // last wins
+    let merged =
+        pnk!(db.transform_keys(&crate::unique_path!(), |k| k.to_string().len()));
+    assert_eq!(2, merged.len());
+    assert_eq!(Some(gen_sample(9)), merged.get(&1));
+    assert_eq!(Some(gen_sample(99)), merged.get(&2));
+}
+
+#[test]
+fn t_transform_values() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100usize).for_each(|i| db.set_value(i, "x".repeat(i)));
+
+    let lens = pnk!(db.transform_values(&crate::unique_path!(), |_, v| v.len()));
+    assert_eq!(db.len(), lens.len());
+    (0..100).for_each(|i| assert_eq!(Some(i), lens.get(&i)));
+
+    pnk!(db.transform_values_in_place(|k, v| format!("{}{}", k, v)));
+    assert_eq!(100, db.len());
+    (0..100)
+        .for_each(|i| assert_eq!(Some(format!("{}{}", i, "x".repeat(i))), db.get(&i)));
+}
+
+#[test]
+fn t_flat_map() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..10usize).for_each(|i| {
+        db.set_value(
+            i,
+            (0..i).map(|j| format!("{}-{}", i, j)).collect::<Vec<_>>(),
+        )
+    });
+
+    let exploded = pnk!(db.flat_map(&crate::unique_path!(), |k, v| v
+        .into_iter()
+        .map(move |s| (s, k))));
+    assert_eq!((0..10).sum::<usize>(), exploded.len());
+    assert_eq!(Some(9), exploded.get(&"9-0".to_owned()));
+    assert_eq!(Some(3), exploded.get(&"3-2".to_owned()));
+    assert!(exploded.get(&"0-0".to_owned()).is_none());
+}
+
+#[test]
+fn t_reduce() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    assert_eq!(1, db.reduce_values(1, |acc, v: u64| acc * v));
+
+    (1..=10u8).for_each(|i| db.set_value(i, i as u64));
+
+    assert_eq!(3628800, db.reduce_values(1, |acc, v| acc * v));
+    assert_eq!(
+        "1,2,3,4,5,6,7,8,9,10,",
+        db.reduce_keys(String::new(), |acc, k| format!("{}{},", acc, k))
+    );
+    assert_eq!(110, db.reduce(0, |acc, k, v| acc + k as u64 + v));
+}
+
+#[test]
+fn t_clone_empty() {
+    crate::clear();
+
+    let mut db = pnk!(Mapx::with_metrics(&crate::unique_path!()));
+    (0..10usize).for_each(|i| db.set_value(i, i));
+
+    let empty = pnk!(db.clone_empty());
+    assert!(empty.is_empty());
+    assert_ne!(db.get_path(), empty.get_path());
+    assert_eq!(
+        0,
+        pnk!(empty.metrics())
+            .insert_count
+            .load(AtomicOrdering::Relaxed)
+    );
+    assert_eq!(10, db.len());
+
+    let path = crate::unique_path!();
+    let empty = pnk!(db.clone_empty_at(&path));
+    assert_eq!(path, empty.get_path());
+    assert!(empty.is_empty());
+
+    let plain: Mapx<usize, usize> = crate::new_mapx!();
+    assert!(pnk!(plain.clone_empty()).metrics().is_none());
+
+    // not empty
+    assert!(db.clone_empty_at(db.get_path()).is_err());
+}
+
+#[test]
+fn t_take_where() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100u8).for_each(|i| db.set_value(i, i));
+
+    let taken = db.take_where(|k, _| 0 == k % 3);
+    assert_eq!(
+        (0..100).step_by(3).map(|i| (i, i)).collect::<Vec<_>>(),
+        taken
+    );
+    assert_eq!(100 - taken.len(), db.len());
+    assert!(db.iter().all(|(k, _)| 0 != k % 3));
+
+    assert!(db.take_where(|_, _| false).is_empty());
+    assert_eq!(66, db.take_where(|_, _| true).len());
+    assert!(db.is_empty());
+
+    // multi-byte keys, the storage order differs from the `Ord`
+    let mut db = crate::new_mapx!();
+    (250..262u32).for_each(|i| db.set_value(i, i));
+    assert_eq!(
+        vec![
+            (250, 250),
+            (252, 252),
+            (254, 254),
+            (256, 256),
+            (258, 258),
+            (260, 260)
+        ],
+        db.take_where(|k, _| 0 == k % 2)
+    );
+    assert_eq!(6, db.len());
+}
+
+#[test]
+fn t_to_from_hashmap() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100usize).for_each(|i| db.set_value(i.to_string(), gen_sample(i)));
+
+    let map = db.to_hashmap();
+    assert_eq!(100, map.len());
+    (0..100).for_each(|i| assert_eq!(Some(&gen_sample(i)), map.get(&i.to_string())));
+
+    let restored = pnk!(Mapx::from_hashmap(map, &crate::unique_path!()));
+    assert_eq!(db.to_sorted_vec(), restored.to_sorted_vec());
+}
+
+#[test]
+fn t_iter_skip() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..100u8).for_each(|i| db.set_value(i, gen_sample(i as usize)));
+
+    let rest = db.iter_skip(50).collect::<Vec<_>>();
+    assert_eq!(50, rest.len());
+    assert_eq!((50, gen_sample(50)), rest[0]);
+    assert_eq!(db.iter().skip(50).collect::<Vec<_>>(), rest);
+
+    assert_eq!(100, db.iter_skip(0).count());
+    assert_eq!(0, db.iter_skip(100).count());
+    assert_eq!(0, db.iter_skip(1000).count());
+
+    assert_eq!(Some(0), db.nth_key(0));
+    assert_eq!(Some(50), db.nth_key(50));
+    assert_eq!(None, db.nth_key(100));
+
+    // multi-byte keys, the position follows the storage order
+    let mut db = crate::new_mapx!();
+    [1u32, 2, 256].iter().for_each(|i| db.set_value(*i, *i));
+    let keys = db.iter().map(|(k, _)| k).collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        (0..3).map(|i| pnk!(db.nth_key(i))).collect::<Vec<_>>()
+    );
+    // the little-endian bytes of 256 are the smallest ones
+    assert_eq!(Some(256), db.nth_key(0));
+    assert_eq!(Some((1, 1)), db.pick_by_rank(&[0])[0]);
+}
+
+#[test]
+fn t_sync_from() {
+    use std::sync::{Arc, RwLock};
+
+    crate::clear();
+
+    let mut source = crate::new_mapx!();
+    let mut dest = crate::new_mapx!();
+    (0..100usize).for_each(|i| source.set_value(i, i));
+    (50..150usize).for_each(|i| dest.set_value(i, i * 2));
+
+    // 0..50 inserted, 50..100 updated, 100..150 removed
+    assert_eq!(150, pnk!(dest.sync_from(&source)));
+    assert_eq!(source.to_sorted_vec(), dest.to_sorted_vec());
+    assert_eq!(0, pnk!(dest.sync_from(&source)));
+
+    let source = Arc::new(RwLock::new(source));
+    let dest = Arc::new(RwLock::new(dest));
+    let handle = Mapx::sync_continuously(
+        Arc::clone(&dest),
+        Arc::clone(&source),
+        Duration::from_millis(10),
+    );
+
+    pnk!(source.write()).set_value(1000, 1000);
+    pnk!(source.write()).unset_value(&0);
+
+    let mut synced = false;
+    for _ in 0..200 {
+        std::thread::sleep(Duration::from_millis(10));
+        if pnk!(source.read()).to_sorted_vec() == pnk!(dest.read()).to_sorted_vec() {
+            synced = true;
+            break;
+        }
+    }
+    handle.stop();
+    assert!(synced);
+
+    // no more syncs after stopped
+    pnk!(source.write()).set_value(2000, 2000);
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(!pnk!(dest.read()).contains_key(&2000));
+}
+
+#[test]
+fn t_bounded_insert() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    let inserted = (0..7u8)
+        .map(|i| pnk!(db.bounded_insert(i, i, 5)))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![true, true, true, true, true, false, false], inserted);
+    assert_eq!(5, db.len());
+    assert!(!db.contains_key(&5));
+
+    // updating is allowed
+    assert!(pnk!(db.bounded_insert(0, 100, 5)));
+    assert_eq!(Some(100), db.get(&0));
+    assert_eq!(5, db.len());
+
+    // 256 is stored before 1 and 2, but it is not the smallest one
+    let mut db = crate::new_mapx!();
+    let evicted = [256u32, 1, 2, 3]
+        .iter()
+        .flat_map(|i| db.bounded_insert_evict_oldest(*i, *i, 2))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![(1, 1), (2, 2)], evicted);
+    assert_eq!(2, db.len());
+    assert_eq!(vec![(3, 3), (256, 256)], db.to_sorted_vec());
+
+    assert!(db.bounded_insert_evict_oldest(3, 30, 2).is_empty());
+    assert!(db.bounded_insert_evict_oldest(100, 100, 0).is_empty());
+    assert!(!db.contains_key(&100));
+
+    // a lower cap evicts until there is room for the new entry
+    (10..20u32).for_each(|i| db.set_value(i, i));
+    assert_eq!(12, db.len());
+    let evicted = db.bounded_insert_evict_oldest(5, 5, 4);
+    assert_eq!(
+        [(3, 30)]
+            .into_iter()
+            .chain((10..18).map(|i| (i, i)))
+            .collect::<Vec<_>>(),
+        evicted
+    );
+    assert_eq!(4, db.len());
+    assert_eq!(
+        vec![(5, 5), (18, 18), (19, 19), (256, 256)],
+        db.to_sorted_vec()
+    );
+}
+
+#[test]
+fn t_into_streaming_iter() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    pnk!(db.atomic_swap_all((0..100_000u64).map(|i| (i, i))));
+    assert_eq!(100_000, db.len());
+
+    let mut it = db.into_streaming_iter();
+    let (mut cnt, mut sum) = (0, 0);
+    for entry in it.by_ref() {
+        let (k, v) = pnk!(entry);
+        assert_eq!(k, v);
+        cnt += 1;
+        sum += v;
+    }
+    assert_eq!(100_000, cnt);
+    assert_eq!((0..100_000).sum::<u64>(), sum);
+    assert!(it.next().is_none());
+}
+
+#[test]
+fn t_count_prefix_hits() {
+    crate::clear();
+
+    #[derive(
+        Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+    )]
+    enum Account {
+        User(u64),
+        Contract(u64),
+    }
+
+    let mut db = crate::new_mapx!();
+    (0..300u64).for_each(|i| {
+        db.insert(Account::User(i), i);
+    });
+    (0..50u64).for_each(|i| {
+        db.insert(Account::Contract(i), i);
+    });
+
+    let user_tag = pnk!(bincode::serialize(&0u32));
+    let contract_tag = pnk!(bincode::serialize(&1u32));
+    assert_eq!(300, pnk!(db.count_prefix_hits(&user_tag)));
+    assert_eq!(50, pnk!(db.count_prefix_hits(&contract_tag)));
+    assert_eq!(350, pnk!(db.count_prefix_hits(&[])));
+    assert_eq!(0, pnk!(db.count_prefix_hits(&[9])));
+
+    db.remove(&Account::Contract(0));
+    assert_eq!(49, pnk!(db.count_prefix_hits(&contract_tag)));
+}
+
+#[test]
+#[cfg(feature = "observe")]
+fn t_register_observer() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+
+    let cnter = Arc::new(AtomicU64::new(0));
+    let removed = Arc::new(Mutex::new(vec![]));
+    let c = Arc::clone(&cnter);
+    db.register_observer(move |_| {
+        c.fetch_add(1, AtomicOrdering::Relaxed);
+    });
+    let r = Arc::clone(&removed);
+    db.register_observer(move |e| {
+        if let MapxChangeEvent::KeyRemoved { key } = e {
+            pnk!(r.lock().map_err(|e| eg!(e))).push(key);
+        }
+    });
+
+    (0..10).for_each(|i| {
+        db.insert(i, i);
+    });
+    (0..3).for_each(|i| {
+        db.remove(&i);
+    });
+    // removing a non-existing key is not a write
+    db.remove(&100);
+
+    assert_eq!(13, cnter.load(AtomicOrdering::Relaxed));
+    assert_eq!(vec![0, 1, 2], *pnk!(removed.lock().map_err(|e| eg!(e))));
+
+    db.remove_all_observers();
+    db.insert(100, 100);
+    assert_eq!(13, cnter.load(AtomicOrdering::Relaxed));
+
+    // an observer can write to the instance again
+    let shadow = Mutex::new(db.clone());
+    db.register_observer(move |e| {
+        if let MapxChangeEvent::KeyInserted { key, value } = e {
+            if 1000 > key {
+                pnk!(shadow.lock().map_err(|e| eg!(e))).insert(key + 1000, value);
+            }
+        }
+    });
+    db.insert(200, 200);
+    assert_eq!(Some(200), db.get(&1200));
+}
+
+#[tokio::test]
+#[cfg(feature = "async")]
+async fn t_iter_in_batches_async() {
+    use futures::StreamExt;
+
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..1000u64).for_each(|i| {
+        db.insert(i, i);
+    });
+
+    let mut batches = Box::pin(db.iter_in_batches_async(64));
+    let (mut cnt, mut sum) = (0, 0);
+    while let Some(batch) = batches.next().await {
+        assert!(batch.len() <= 64);
+        for (_, v) in batch {
+            cnt += 1;
+            sum += v;
+        }
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(1000, cnt);
+    assert_eq!((0..1000).sum::<u64>(), sum);
+
+    assert_eq!(16, db.iter_in_batches_async(64).count().await);
+    assert_eq!(0, db.iter_in_batches_async(0).count().await);
+}
+
+#[test]
+fn t_raw_bytes() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    db.insert(1u32, gen_sample(1));
+
+    (2..10u32).for_each(|i| {
+        db.insert_raw_bytes(
+            pnk!(bincode::serialize(&i)),
+            pnk!(serde_json::to_vec(&gen_sample(i as usize))),
+        );
+    });
+    assert_eq!(9, db.len());
+    (1..10u32).for_each(|i| assert_eq!(Some(gen_sample(i as usize)), db.get(&i)));
+
+    // overwriting an existing key does not change the length
+    db.insert_raw_bytes(
+        pnk!(bincode::serialize(&1u32)),
+        pnk!(serde_json::to_vec(&gen_sample(100))),
+    );
+    assert_eq!(9, db.len());
+    assert_eq!(Some(gen_sample(100)), db.get(&1));
+
+    let raw = db.get_raw_bytes(&pnk!(bincode::serialize(&5u32)));
+    assert_eq!(Some(pnk!(serde_json::to_vec(&gen_sample(5)))), raw);
+    assert!(db
+        .get_raw_bytes(&pnk!(bincode::serialize(&100u32)))
+        .is_none());
+}
+
+#[test]
+fn t_debug_dump() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+    (0..3u32).for_each(|i| {
+        db.insert(i, format!("v{}", i));
+    });
+
+    let mut buf = vec![];
+    pnk!(db.debug_dump_to(&mut buf));
+    let out = pnk!(String::from_utf8(buf));
+    let lines = out.lines().collect::<Vec<_>>();
+
+    assert_eq!(4, lines.len());
+    assert!(lines[0].contains(db.get_path()));
+    assert!(lines[0].contains("3 entries"));
+    assert_eq!(r#"0 => "v0""#, lines[1]);
+    assert_eq!(r#"1 => "v1""#, lines[2]);
+    assert_eq!(r#"2 => "v2""#, lines[3]);
+
+    db.debug_dump();
+}
+
+#[test]
+fn t_capacity_hint() {
+    crate::clear();
+
+    let mut db = crate::new_mapx!();
+
+    let (size, num) = pnk!(db.in_disk.capacity_hint(0));
+    assert_eq!(64 * 1024 * 1024, size);
+    assert_eq!(2, num);
+    let (size, num) = pnk!(db.in_disk.capacity_hint(usize::MAX));
+    assert_eq!(256 * 1024 * 1024, size);
+    assert_eq!(4, num);
+    pnk!(db.reset_capacity_hint());
+
+    let (size, _) = pnk!(db.in_disk.capacity_hint(100_000));
+    assert!(64 * 1024 * 1024 <= size);
+    assert!(256 * 1024 * 1024 >= size);
+    pnk!(db.capacity_hint(100_000));
+    (0..100_000usize).for_each(|i| db.set_value(i, i));
+    assert_eq!(100_000, db.len());
+    assert!(pnk!(db.average_entry_size_bytes()) > 0);
+    pnk!(db.reset_capacity_hint());
+
+    (0..crate::DB_NUM).for_each(|i| {
+        assert_eq!(
+            0,
+            crate::helper::rocksdb_property(i, "rocksdb.is-write-stopped")
+        );
+        assert_eq!(
+            0,
+            crate::helper::rocksdb_property(i, "rocksdb.actual-delayed-write-rate")
+        );
+    });
+}
+
+#[test]
+fn t_atomic_rmw() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let mut db: Mapx<u32, u64> = pnk!(Mapx::new(&path));
+
+    assert_eq!(None, pnk!(db.atomic_rmw(1, |v| Some(v.unwrap_or(0) + 1))));
+    assert_eq!(Some(1), pnk!(db.atomic_rmw(1, |v| v.map(|v| v + 1))));
+    assert_eq!(Some(2), db.get(&1));
+    assert_eq!(Some(2), pnk!(db.atomic_rmw(1, |_| None)));
+    assert!(db.get(&1).is_none());
+    assert_eq!(0, db.len());
+
+    // a write between the snapshot and the check is a conflict
+    let mut other = db.clone();
+    db.insert(2, 0);
+    assert!(db
+        .atomic_rmw(2, |v| {
+            other.insert(2, 100);
+            v.map(|v| v + 1)
+        })
+        .is_err());
+    assert_eq!(Some(100), db.get(&2));
+    assert_eq!(1, db.len());
+
+    let n = 200;
+    let workers = (0..2)
+        .map(|_| {
+            let mut db = db.clone();
+            thread::spawn(move || {
+                for _ in 0..n {
+                    // retry until no conflict
+                    while db.atomic_rmw(3, |v| Some(v.unwrap_or(0) + 1)).is_err() {}
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    workers
+        .into_iter()
+        .for_each(|w| pnk!(w.join().map_err(|e| eg!(format!("{:?}", e)))));
+
+    assert_eq!(Some(2 * n), db.get(&3));
+    assert_eq!(2, db.len());
+}
@@ -107,6 +107,19 @@ where
         self.in_disk.set_value(key, value);
     }
 
+    /// Imitate the behavior of 'BTreeMap<_>.try_insert(...)',
+    /// the existing value will never be overwritten,
+    /// and the given <K, V> will be returned back in the error.
+    #[inline(always)]
+    pub fn try_insert(&mut self, key: K, value: V) -> std::result::Result<(), (K, V)> {
+        if self.contains_key(&key) {
+            Err((key, value))
+        } else {
+            self.set_value(key, value);
+            Ok(())
+        }
+    }
+
     /// Imitate the behavior of '.entry(...).or_insert(...)'
     #[inline(always)]
     pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
@@ -384,6 +397,7 @@ where
     {
         let v = pnk!(serde_json::to_string(&CacheMeta {
             path: self.get_path(),
+            namespace: None,
         }));
 
         serializer.serialize_str(&v)
@@ -91,3 +91,22 @@ fn t_mapxnk() {
     assert_eq!(100, db_restore.get_closest_smaller(&100).unwrap().1.idx);
     assert_eq!(100, db_restore.get_closest_smaller(&101).unwrap().1.idx);
 }
+
+#[test]
+fn t_try_insert() {
+    crate::clear();
+
+    let mut db = crate::new_mapxnk!();
+
+    // vacant
+    assert!(db.try_insert(1usize, gen_sample(1)).is_ok());
+    assert_eq!(1, db.len());
+
+    // occupied, the old value is kept
+    assert_eq!(db.try_insert(1, gen_sample(2)), Err((1, gen_sample(2))));
+    assert_eq!(pnk!(db.get(&1)), gen_sample(1));
+    assert_eq!(1, db.len());
+
+    assert!(db.try_insert(2, gen_sample(2)).is_ok());
+    assert_eq!(2, db.len());
+}
@@ -26,4 +26,7 @@ impl<'de> serde::de::Visitor<'de> for CacheVisitor {
 #[derive(Deserialize, Serialize)]
 pub(crate) struct CacheMeta<'a> {
     pub path: &'a str,
+    // The namespace prefix of a Mapx, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<Vec<u8>>,
 }
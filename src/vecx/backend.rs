@@ -3,10 +3,21 @@
 //!
 
 use crate::{helper::*, DB_NUM};
-use rocksdb::DBIterator;
+use rocksdb::{DBIterator, WriteBatch};
 use ruc::*;
 use serde::{de::DeserializeOwned, Serialize};
-use std::{convert::TryInto, fmt, iter::Iterator, marker::PhantomData, mem::size_of};
+use std::{
+    convert::TryInto,
+    fmt,
+    iter::Iterator,
+    marker::PhantomData,
+    mem::size_of,
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 /// To solve the problem of unlimited memory usage,
 /// use this to replace the original in-memory `Vec<_>`.
@@ -19,7 +30,7 @@ where
     T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
 {
     path: String,
-    cnter: usize,
+    cnter: Arc<AtomicUsize>,
     prefix: Vec<u8>,
     idx: usize,
     _pd: PhantomData<T>,
@@ -44,7 +55,7 @@ where
 
         Ok(Vecx {
             path: path.to_owned(),
-            cnter: BNC[idx].prefix_iterator(&prefix).count(),
+            cnter: Arc::new(AtomicUsize::new(BNC[idx].prefix_iterator(&prefix).count())),
             prefix,
             idx,
             _pd: PhantomData,
@@ -70,6 +81,12 @@ where
             .map(|bytes| pnk!(serde_json::from_slice(&bytes)))
     }
 
+    /// Imitate the behavior of 'Vec<_>.first()'
+    #[inline(always)]
+    pub(super) fn first(&self) -> Option<T> {
+        self.get(0)
+    }
+
     /// Imitate the behavior of 'Vec<_>.last()'
     #[inline(always)]
     pub(super) fn last(&self) -> Option<T> {
@@ -81,9 +98,9 @@ where
     pub(super) fn len(&self) -> usize {
         debug_assert_eq!(
             BNC[self.idx].prefix_iterator(&self.prefix).count(),
-            self.cnter
+            self.cnter.load(Ordering::Relaxed)
         );
-        self.cnter
+        self.cnter.load(Ordering::Relaxed)
     }
 
     /// A helper func
@@ -95,7 +112,7 @@ where
     /// Imitate the behavior of 'Vec<_>.push(...)'
     #[inline(always)]
     pub(super) fn push(&mut self, b: T) {
-        let idx = self.cnter;
+        let idx = self.cnter.load(Ordering::Relaxed);
 
         let mut k = self.prefix.clone();
         k.extend_from_slice(&idx.to_le_bytes()[..]);
@@ -103,9 +120,59 @@ where
 
         pnk!(BNC[self.idx].put(k, value));
 
-        // There has no `remove`-like methods provided,
+        // The only `remove`-like method is `swap_remove_at`,
+        // which keeps the indexes continuous,
         // so we can increase this value directly.
-        self.cnter += 1;
+        self.cnter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Push all the values in one batch.
+    pub(super) fn push_batch<I>(&mut self, values: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut batch = WriteBatch::default();
+        let mut idx = self.cnter.load(Ordering::Relaxed);
+
+        for b in values {
+            let mut k = self.prefix.clone();
+            k.extend_from_slice(&idx.to_le_bytes()[..]);
+            batch.put(k, serde_json::to_vec(&b).c(d!())?);
+            idx += 1;
+        }
+
+        BNC[self.idx].write(batch).c(d!())?;
+        self.cnter.store(idx, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Similar with `push`, but the value is added into a batch,
+    /// at the position of `offset` after the current end;
+    /// the counter is updated by the caller after the batch has been written.
+    #[inline(always)]
+    pub(super) fn batch_push(
+        &self,
+        batches: &mut [WriteBatch],
+        offset: usize,
+        b: T,
+    ) -> Result<()> {
+        let idx = self.cnter.load(Ordering::Relaxed) + offset;
+
+        let mut k = self.prefix.clone();
+        k.extend_from_slice(&idx.to_le_bytes()[..]);
+        let value = serde_json::to_vec(&b).c(d!())?;
+
+        batches[self.idx].put(k, value);
+
+        Ok(())
+    }
+
+    /// The db index and the prefix identify an instance,
+    /// the counter is shared by all the clones of it.
+    #[inline(always)]
+    pub(super) fn counter(&self) -> ((usize, Vec<u8>), Arc<AtomicUsize>) {
+        ((self.idx, self.prefix.clone()), Arc::clone(&self.cnter))
     }
 
     /// Imitate the behavior of 'Vec<_>.insert(idx, value)'
@@ -116,13 +183,69 @@ where
         let value = pnk!(serde_json::to_vec(&b));
         pnk!(BNC[self.idx].put(k, value));
 
-        if idx >= self.cnter {
-            // There has no `remove` like methods provided,
+        if idx >= self.cnter.load(Ordering::Relaxed) {
+            // `swap_remove_at` keeps the indexes continuous,
             // so we can increase this value directly.
-            self.cnter += 1;
+            self.cnter.fetch_add(1, Ordering::Relaxed);
         }
     }
 
+    /// Copy the values of `src` to the indexes starting at `dst`,
+    /// all the values are read before writing, so the ranges may overlap.
+    pub(super) fn copy_within(&mut self, src: Range<usize>, dst: usize) -> Result<()> {
+        if src.start > src.end || src.end > self.cnter.load(Ordering::Relaxed) {
+            return Err(eg!("source range out of bounds"));
+        }
+        if dst > self.cnter.load(Ordering::Relaxed) {
+            return Err(eg!("destination out of bounds"));
+        }
+
+        let values = BNC[self.idx].multi_get(src.clone().map(|i| {
+            let mut k = self.prefix.clone();
+            k.extend_from_slice(&i.to_le_bytes()[..]);
+            k
+        }));
+
+        let mut batch = WriteBatch::default();
+        for (i, v) in (dst..).zip(values) {
+            let mut k = self.prefix.clone();
+            k.extend_from_slice(&i.to_le_bytes()[..]);
+            batch.put(k, v.c(d!())?.c(d!())?);
+        }
+        BNC[self.idx].write(batch).c(d!())?;
+
+        self.cnter.fetch_max(dst + src.len(), Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Imitate the behavior of 'Vec<_>.swap_remove(...)',
+    /// the moving of the last value and the deletion are done in one batch.
+    pub(super) fn swap_remove_at(&mut self, idx: usize) -> Result<Option<T>> {
+        if idx >= self.cnter.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let last_idx = self.cnter.load(Ordering::Relaxed) - 1;
+        let mut k = self.prefix.clone();
+        k.extend_from_slice(&idx.to_le_bytes()[..]);
+        let v = BNC[self.idx].get(&k).c(d!())?.c(d!())?;
+
+        let mut batch = WriteBatch::default();
+        let mut last_k = self.prefix.clone();
+        last_k.extend_from_slice(&last_idx.to_le_bytes()[..]);
+        if idx != last_idx {
+            let last_v = BNC[self.idx].get(&last_k).c(d!())?.c(d!())?;
+            batch.put(k, last_v);
+        }
+        batch.delete(last_k);
+        BNC[self.idx].write(batch).c(d!())?;
+
+        self.cnter.fetch_sub(1, Ordering::Relaxed);
+
+        serde_json::from_slice(&v).c(d!()).map(Some)
+    }
+
     /// Imitate the behavior of '.iter()'
     #[inline(always)]
     pub(super) fn iter(&self) -> VecxIter<'_, T> {
@@ -158,9 +281,11 @@ where
 {
     type Item = (usize, T);
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|(idx, v)| {
+        self.iter.next().map(|(k, v)| {
+            // the index is stored after the prefix of the instance
+            let idx = &k[k.len() - size_of::<usize>()..];
             (
-                usize::from_le_bytes(idx[..size_of::<usize>()].try_into().unwrap()),
+                usize::from_le_bytes(idx.try_into().unwrap()),
                 pnk!(serde_json::from_slice(&v)),
             )
         })
@@ -185,7 +310,7 @@ where
     T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
 {
     fn eq(&self, other: &Vecx<T>) -> bool {
-        !self.iter().zip(other.iter()).any(|(i, j)| i != j)
+        self.len() == other.len() && !self.iter().zip(other.iter()).any(|(i, j)| i != j)
     }
 }
 
@@ -0,0 +1,108 @@
+//!
+//! # A fixed-size rolling window based on Vecx
+//!
+
+use super::Vecx;
+use ruc::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+
+/// A ring buffer with the capacity `N`,
+/// the oldest value will be overwritten when it is full.
+///
+/// Each value is stored with a sequence number,
+/// so the position of the oldest value can be restored when reopening,
+/// this costs a scan over all the `N` values.
+#[derive(PartialEq, Debug, Clone)]
+pub struct VecxFixed<V, const N: usize>
+where
+    V: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    inner: Vecx<(u64, V)>,
+    // index of the oldest value
+    head: usize,
+    next_seq: u64,
+}
+
+impl<V, const N: usize> VecxFixed<V, N>
+where
+    V: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    /// Create an instance.
+    pub fn new(path: &str) -> Result<Self> {
+        if 0 == N {
+            return Err(eg!("the capacity can not be zero"));
+        }
+
+        let inner = Vecx::new(path).c(d!())?;
+
+        let mut head = 0;
+        let mut next_seq = inner.len() as u64;
+        if N == inner.len() {
+            let mut min_seq = u64::MAX;
+            for (i, (seq, _)) in inner.enumerate() {
+                if seq < min_seq {
+                    min_seq = seq;
+                    head = i;
+                }
+                next_seq = next_seq.max(seq + 1);
+            }
+        }
+
+        Ok(VecxFixed {
+            inner,
+            head,
+            next_seq,
+        })
+    }
+
+    /// Get the database storage path
+    pub fn get_path(&self) -> &str {
+        self.inner.get_path()
+    }
+
+    /// The number of values, at most `N`.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// A helper func
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Append a value, the oldest one will be overwritten if it is full.
+    pub fn push(&mut self, value: V) {
+        if N > self.inner.len() {
+            self.inner.push((self.next_seq, value));
+        } else {
+            self.inner.set_value(self.head, (self.next_seq, value));
+            self.head = (self.head + 1) % N;
+        }
+        self.next_seq += 1;
+    }
+
+    /// Get a value by its offset, `0` means the oldest one.
+    #[inline(always)]
+    pub fn get(&self, offset: usize) -> Option<V> {
+        if offset < self.len() {
+            self.inner.get((self.head + offset) % N).map(|(_, v)| v)
+        } else {
+            None
+        }
+    }
+
+    /// Get the newest value.
+    #[inline(always)]
+    pub fn latest(&self) -> Option<V> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    /// Iterate from the oldest value to the newest one.
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = V> + '_ {
+        (0..self.len()).filter_map(move |i| self.get(i))
+    }
+}
@@ -5,19 +5,25 @@
 //!
 
 mod backend;
+mod fixed;
 
 #[cfg(test)]
 mod test;
 
+pub use fixed::VecxFixed;
+
+#[cfg(not(feature = "serialize-full"))]
 use crate::serde::{CacheMeta, CacheVisitor};
+use rocksdb::WriteBatch;
 use ruc::*;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     cmp::Ordering,
     fmt,
-    iter::Iterator,
+    iter::{FusedIterator, Iterator},
     mem::ManuallyDrop,
-    ops::{Deref, DerefMut},
+    ops::{Bound, Deref, DerefMut, RangeBounds},
+    sync::{atomic::AtomicUsize, Arc},
 };
 
 /// To solve the problem of unlimited memory usage,
@@ -69,12 +75,31 @@ where
             .map(move |v| ValueMut::new(self, idx, v))
     }
 
+    /// Imitate the behavior of 'Vec<_>.first()'
+    #[inline(always)]
+    pub fn first(&self) -> Option<T> {
+        self.in_disk.first()
+    }
+
+    /// Imitate the behavior of 'Vec<_>.first_mut()'
+    #[inline(always)]
+    pub fn first_mut(&mut self) -> Option<ValueMut<'_, T>> {
+        self.get_mut(0)
+    }
+
     /// Imitate the behavior of 'Vec<_>.last()'
     #[inline(always)]
     pub fn last(&self) -> Option<T> {
         self.in_disk.last()
     }
 
+    /// Imitate the behavior of 'Vec<_>.last_mut()'
+    #[inline(always)]
+    pub fn last_mut(&mut self) -> Option<ValueMut<'_, T>> {
+        let idx = self.len().checked_sub(1)?;
+        self.get_mut(idx)
+    }
+
     /// Imitate the behavior of 'Vec<_>.len()'
     #[inline(always)]
     pub fn len(&self) -> usize {
@@ -93,6 +118,45 @@ where
         self.in_disk.push(b);
     }
 
+    /// Imitate the behavior of 'Vec<_>.extend_from_slice(...)',
+    /// all the values are written in one batch.
+    #[inline(always)]
+    pub fn extend_from_slice(&mut self, values: &[T]) -> Result<()> {
+        self.push_batch(values.iter().cloned()).c(d!())
+    }
+
+    /// Append all the values of `other` in the order of index, in one batch.
+    #[inline(always)]
+    pub fn extend_from_vecx(&mut self, other: &Vecx<T>) -> Result<()> {
+        self.push_batch(other.enumerate().map(|(_, v)| v)).c(d!())
+    }
+
+    // Push all the values in one batch.
+    #[inline(always)]
+    pub(crate) fn push_batch<I>(&mut self, values: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.in_disk.push_batch(values).c(d!())
+    }
+
+    // Used by [WriteBatchBuilder](crate::batch::WriteBatchBuilder).
+    #[inline(always)]
+    pub(crate) fn batch_push(
+        &self,
+        batches: &mut [WriteBatch],
+        offset: usize,
+        b: T,
+    ) -> Result<()> {
+        self.in_disk.batch_push(batches, offset, b).c(d!())
+    }
+
+    // Used by [WriteBatchBuilder](crate::batch::WriteBatchBuilder).
+    #[inline(always)]
+    pub(crate) fn counter(&self) -> ((usize, Vec<u8>), Arc<AtomicUsize>) {
+        self.in_disk.counter()
+    }
+
     /// Imitate the behavior of 'Vec<_>.insert(idx, value)',
     /// but we do not return the previous value, like `Vecx<_, _>.set_value`.
     #[inline(always)]
@@ -100,12 +164,171 @@ where
         self.in_disk.insert(idx, b);
     }
 
+    /// Imitate the behavior of 'slice::copy_within(...)',
+    /// the ranges may overlap, and all the writes are done in one batch.
+    ///
+    /// Unlike the slice version, the copied values may go beyond the end,
+    /// then the instance will be extended, so `dst` can be equal to `len()`.
+    pub fn copy_within<R: RangeBounds<usize>>(
+        &mut self,
+        src: R,
+        dst: usize,
+    ) -> Result<()> {
+        let start = match src.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match src.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.len(),
+        };
+        self.in_disk.copy_within(start..end, dst).c(d!())
+    }
+
+    /// Imitate the behavior of 'Vec<_>.swap_remove(...)',
+    /// the value at `idx` is replaced by the last value, so this is O(1),
+    /// but the order of the values is not preserved.
+    ///
+    /// `None` will be returned if `idx` is out of bounds.
+    #[inline(always)]
+    pub fn swap_remove_at(&mut self, idx: usize) -> Option<T> {
+        pnk!(self.in_disk.swap_remove_at(idx))
+    }
+
+    /// Imitate the behavior of 'Vec<_>.retain(...)',
+    /// but the values are removed by `swap_remove_at`,
+    /// so the order of the remaining values is not preserved.
+    pub fn unordered_retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut i = 0;
+        while i < self.len() {
+            let v = pnk!(self.get(i));
+            if f(&v) {
+                i += 1;
+            } else {
+                // check the value moved from the tail at the same index
+                self.swap_remove_at(i);
+            }
+        }
+    }
+
+    /// Imitate the behavior of 'slice::partition_point(...)',
+    /// the values are read by index in a binary search,
+    /// so only O(log N) values will be read.
+    ///
+    /// The values should be partitioned by `pred`,
+    /// or the result is meaningless.
+    pub fn partition_point<P>(&self, pred: P) -> usize
+    where
+        P: Fn(&T) -> bool,
+    {
+        let (mut left, mut right) = (0, self.len());
+        while left < right {
+            let mid = left + (right - left) / 2;
+            if pred(&pnk!(self.get(mid))) {
+                left = mid + 1;
+            } else {
+                right = mid;
+            }
+        }
+        left
+    }
+
+    /// Imitate the behavior of 'slice::split_at(...)',
+    /// but two iterators over `[0, mid)` and `[mid, len)` are returned,
+    /// the values are read lazily by index.
+    ///
+    /// This will panic if `mid > len`.
+    pub fn split_at(&self, mid: usize) -> (VecxRangeIter<'_, T>, VecxRangeIter<'_, T>) {
+        let len = self.len();
+        assert!(mid <= len, "mid({}) is out of bounds({})", mid, len);
+        (
+            VecxRangeIter {
+                hdr: self,
+                start: 0,
+                end: mid,
+            },
+            VecxRangeIter {
+                hdr: self,
+                start: mid,
+                end: len,
+            },
+        )
+    }
+
+    /// Imitate the behavior of '[start..end].iter()',
+    /// the values are read lazily in the order of index.
+    ///
+    /// This will panic if `start > end` or `end > len`.
+    pub fn iter_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Box<dyn Iterator<Item = T> + '_> {
+        let len = self.len();
+        assert!(start <= end, "start({}) is larger than end({})", start, end);
+        assert!(end <= len, "end({}) is out of bounds({})", end, len);
+        Box::new(VecxRangeIter {
+            hdr: self,
+            start,
+            end,
+        })
+    }
+
+    /// Imitate the behavior of '.iter().enumerate()',
+    /// the values are yielded in the order of index.
+    #[inline(always)]
+    pub fn enumerate(&self) -> VecxEnumerate<'_, T> {
+        VecxEnumerate {
+            hdr: self,
+            idx: 0,
+            len: self.len(),
+        }
+    }
+
+    /// Imitate the behavior of '.iter().enumerate().rev()',
+    /// the values are read one by one from the last index.
+    #[inline(always)]
+    pub fn iter_enumerate_rev(&self) -> impl Iterator<Item = (usize, T)> + '_ {
+        (0..self.len())
+            .rev()
+            .filter_map(move |i| self.get(i).map(|v| (i, v)))
+    }
+
     /// Imitate the behavior of '.iter()'
     #[inline(always)]
     pub fn iter(&self) -> Box<dyn Iterator<Item = T> + '_> {
-        Box::new(VecxIter {
+        Box::new(self.iter_exact())
+    }
+
+    /// Same as `iter()`, but the concrete iterator is returned,
+    /// which implements `ExactSizeIterator` and `FusedIterator`.
+    #[inline(always)]
+    pub fn iter_exact(&self) -> VecxIter<'_, T> {
+        VecxIter {
             iter: self.in_disk.iter(),
-        })
+            len: self.len(),
+        }
+    }
+}
+
+impl<T> Vecx<Vec<T>>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    /// Imitate the behavior of '.iter().flatten()',
+    /// all the inner values are appended to a new instance at `dest_path` in order,
+    /// in one batch.
+    #[inline(always)]
+    pub fn flatten(&self, dest_path: &str) -> Result<Vecx<T>> {
+        let mut db = Vecx::new(dest_path).c(d!())?;
+        db.push_batch(self.enumerate().flat_map(|(_, v)| v))
+            .c(d!())
+            .map(|_| db)
     }
 }
 
@@ -231,6 +454,7 @@ where
     T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
 {
     iter: backend::VecxIter<'a, T>,
+    len: usize,
 }
 
 impl<'a, T> Iterator for VecxIter<'a, T>
@@ -239,8 +463,115 @@ where
 {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
+        if 0 == self.len {
+            return None;
+        }
+        self.len -= 1;
         self.iter.next().map(|v| v.1)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+// The length is captured when the iterator is created,
+// the collection can not be changed during the iteration.
+impl<'a, T> ExactSizeIterator for VecxIter<'a, T> where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug
+{
+}
+
+impl<'a, T> FusedIterator for VecxIter<'a, T> where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug
+{
+}
+
+/// Iter over [Vecx](self::Vecx) with the index of each value,
+/// the values are read one by one in the order of index.
+pub struct VecxEnumerate<'a, T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    hdr: &'a Vecx<T>,
+    idx: usize,
+    len: usize,
+}
+
+impl<'a, T> Iterator for VecxEnumerate<'a, T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Item = (usize, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx < self.len {
+            let idx = self.idx;
+            self.idx += 1;
+            self.hdr.get(idx).map(|v| (idx, v))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.len - self.idx;
+        (n, Some(n))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for VecxEnumerate<'a, T> where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug
+{
+}
+
+/// Iter over a range of indexes of [Vecx](self::Vecx),
+/// the values are read one by one, see `Vecx::split_at()` and `Vecx::iter_range()`.
+pub struct VecxRangeIter<'a, T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    hdr: &'a Vecx<T>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a, T> Iterator for VecxRangeIter<'a, T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start < self.end {
+            self.start += 1;
+            self.hdr.get(self.start - 1)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.end - self.start;
+        (n, Some(n))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for VecxRangeIter<'a, T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start < self.end {
+            self.end -= 1;
+            self.hdr.get(self.end)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for VecxRangeIter<'a, T> where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug
+{
 }
 
 /**********************************************/
@@ -260,10 +591,45 @@ impl<T> Eq for Vecx<T> where
 // End of the implementation of Eq for Vecx //
 //////////////////////////////////////////////
 
+/////////////////////////////////////////////////////
+// Begin of the implementation of TryFrom for Vecx //
+/***************************************************/
+
+impl<T> TryFrom<Vecx<T>> for Vec<T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Error = Box<dyn RucError>;
+
+    // `iter()` follows the storage order, which differs from the order of index
+    fn try_from(v: Vecx<T>) -> Result<Self> {
+        Ok(v.enumerate().map(|(_, v)| v).collect())
+    }
+}
+
+// The data will be appended to the Vecx at the given path.
+impl<T> TryFrom<(Vec<T>, &str)> for Vecx<T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    type Error = Box<dyn RucError>;
+
+    fn try_from((v, path): (Vec<T>, &str)) -> Result<Self> {
+        let mut hdr = Vecx::new(path).c(d!())?;
+        hdr.in_disk.push_batch(v).c(d!())?;
+        Ok(hdr)
+    }
+}
+
+/*************************************************/
+// End of the implementation of TryFrom for Vecx //
+///////////////////////////////////////////////////
+
 ///////////////////////////////////////////////////////////////////
 // Begin of the implementation of Serialize/Deserialize for Vecx //
 /*****************************************************************/
 
+#[cfg(not(feature = "serialize-full"))]
 impl<'a, T> serde::Serialize for Vecx<T>
 where
     T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
@@ -274,12 +640,14 @@ where
     {
         let v = pnk!(serde_json::to_string(&CacheMeta {
             path: self.get_path(),
+            namespace: None,
         }));
 
         serializer.serialize_str(&v)
     }
 }
 
+#[cfg(not(feature = "serialize-full"))]
 impl<'de, T> serde::Deserialize<'de> for Vecx<T>
 where
     T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
@@ -295,6 +663,39 @@ where
     }
 }
 
+// Serialize all the elements as a sequence in the order of index,
+// so the data can be transported to other processes.
+#[cfg(feature = "serialize-full")]
+impl<T> serde::Serialize for Vecx<T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.enumerate().map(|(_, v)| v))
+    }
+}
+
+// A new instance will be created at a unique path.
+#[cfg(feature = "serialize-full")]
+impl<'de, T> serde::Deserialize<'de> for Vecx<T>
+where
+    T: PartialEq + Clone + Serialize + DeserializeOwned + fmt::Debug,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data = <Vec<T> as serde::Deserialize>::deserialize(deserializer)?;
+        let mut db = Vecx::new(&crate::unique_path!())
+            .map_err(|e| serde::de::Error::custom(e.to_string()))?;
+        data.into_iter().for_each(|v| db.push(v));
+        Ok(db)
+    }
+}
+
 /***************************************************************/
 // End of the implementation of Serialize/Deserialize for Vecx //
 /////////////////////////////////////////////////////////////////
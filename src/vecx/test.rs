@@ -27,7 +27,12 @@ fn t_vecx() {
     let cnt = 200;
 
     let db = {
-        omit!(fs::remove_dir_all("/tmp/bnc_test/Vecx"));
+        // the meta of the path is kept by `crate::clear()`
+        omit!(fs::remove_dir_all(format!(
+            "{}/{}//tmp/bnc_test/Vecx",
+            crate::BNC_DATA_DIR.as_str(),
+            crate::BNC_META_NAME
+        )));
         let mut db = crate::new_vecx!("/tmp/bnc_test/Vecx");
 
         assert_eq!(0, db.len());
@@ -68,3 +73,404 @@ fn t_vecx() {
     crate::clear();
     assert!(db_restore.is_empty());
 }
+
+#[test]
+fn t_first_last() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+
+    assert!(db.first().is_none());
+    assert!(db.last().is_none());
+    assert!(db.first_mut().is_none());
+    assert!(db.last_mut().is_none());
+
+    db.push(gen_sample(0));
+    assert_eq!(pnk!(db.first()), gen_sample(0));
+    assert_eq!(pnk!(db.last()), gen_sample(0));
+
+    (1..10).for_each(|i| db.push(gen_sample(i)));
+    assert_eq!(pnk!(db.first()), gen_sample(0));
+    assert_eq!(pnk!(db.last()), gen_sample(9));
+
+    *pnk!(db.first_mut()) = gen_sample(100);
+    *pnk!(db.last_mut()) = gen_sample(900);
+    assert_eq!(pnk!(db.first()), gen_sample(100));
+    assert_eq!(pnk!(db.last()), gen_sample(900));
+}
+
+#[cfg(feature = "serialize-full")]
+#[test]
+fn t_serialize_full() {
+    crate::clear();
+
+    // more than 256 elements, the order of index differs from the storage order
+    let mut db = crate::new_vecx!();
+    (0..300).for_each(|i| db.push(gen_sample(i)));
+
+    let json = pnk!(serde_json::to_string(&db));
+    let value = pnk!(serde_json::from_str::<serde_json::Value>(&json));
+    assert_eq!(300, pnk!(value.as_array()).len());
+
+    let db_restore = pnk!(serde_json::from_str::<Vecx<SampleBlock>>(&json));
+    assert_ne!(db.get_path(), db_restore.get_path());
+    assert_eq!(300, db_restore.len());
+    (0..300).for_each(|i| {
+        assert_eq!(db_restore.get(i), Some(gen_sample(i)));
+    });
+}
+
+#[test]
+fn t_enumerate() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    assert_eq!(0, db.enumerate().len());
+
+    (0..1000).for_each(|i| db.push(gen_sample(i)));
+
+    let mut it = db.enumerate();
+    assert_eq!(db.len(), it.len());
+    it.next();
+    assert_eq!(db.len() - 1, it.len());
+
+    db.enumerate()
+        .for_each(|(i, v)| assert_eq!(gen_sample(i), v));
+    assert_eq!(
+        (0..1000).collect::<Vec<_>>(),
+        db.enumerate().map(|(i, _)| i).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn t_iter_len() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    assert_eq!(0, db.iter_exact().len());
+
+    (0..100).for_each(|i| db.push(gen_sample(i)));
+
+    let mut it = db.iter_exact();
+    assert_eq!(100, it.len());
+    (0..40).for_each(|_| assert!(it.next().is_some()));
+    assert_eq!(60, it.len());
+    assert_eq!(60, it.by_ref().count());
+    assert_eq!(0, it.len());
+    assert!(it.next().is_none());
+    assert!(it.next().is_none());
+}
+
+#[test]
+fn t_iter_enumerate_rev() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    assert!(db.iter_enumerate_rev().next().is_none());
+
+    (0..5).for_each(|i| db.push(gen_sample(i)));
+    assert_eq!(
+        (0..5).rev().map(|i| (i, gen_sample(i))).collect::<Vec<_>>(),
+        db.iter_enumerate_rev().collect::<Vec<_>>()
+    );
+
+    (5..1000).for_each(|i| db.push(gen_sample(i)));
+    assert_eq!(
+        (0..1000).rev().collect::<Vec<_>>(),
+        db.iter_enumerate_rev().map(|(i, _)| i).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn t_vecx_fixed() {
+    crate::clear();
+
+    const N: usize = 100;
+
+    let path = crate::unique_path!();
+    let mut db = pnk!(VecxFixed::<usize, N>::new(&path));
+    assert!(db.is_empty());
+    assert!(db.latest().is_none());
+
+    (0..50).for_each(|i| db.push(i));
+    assert_eq!(50, db.len());
+    assert_eq!(Some(0), db.get(0));
+    assert_eq!(Some(49), db.latest());
+
+    (50..N + 10).for_each(|i| db.push(i));
+    assert_eq!(N, db.len());
+    assert_eq!(Some(10), db.get(0));
+    assert_eq!(Some(N + 9), db.latest());
+    assert!(db.get(N).is_none());
+    assert_eq!(
+        (10..N + 10).collect::<Vec<_>>(),
+        db.iter().collect::<Vec<_>>()
+    );
+
+    let mut db = pnk!(VecxFixed::<usize, N>::new(&path));
+    assert_eq!(
+        (10..N + 10).collect::<Vec<_>>(),
+        db.iter().collect::<Vec<_>>()
+    );
+    db.push(N + 10);
+    assert_eq!(Some(11), db.get(0));
+    assert_eq!(Some(N + 10), db.latest());
+
+    assert!(VecxFixed::<usize, 0>::new(&crate::unique_path!()).is_err());
+}
+
+#[test]
+fn t_try_from() {
+    crate::clear();
+
+    let path = crate::unique_path!();
+    let data = (0..300).map(gen_sample).collect::<Vec<_>>();
+
+    let db: Vecx<SampleBlock> = pnk!((data.clone(), path.as_str()).try_into());
+    assert_eq!(300, db.len());
+    assert_eq!(path, db.get_path());
+    (0..300).for_each(|i| assert_eq!(Some(&data[i]), db.get(i).as_ref()));
+
+    let restored: Vec<SampleBlock> = pnk!(db.try_into());
+    assert_eq!(data, restored);
+}
+
+#[test]
+fn t_swap_remove_at() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    assert!(db.swap_remove_at(0).is_none());
+
+    (0..10).for_each(|i| db.push(gen_sample(i)));
+    assert_eq!(Some(gen_sample(2)), db.swap_remove_at(2));
+    assert_eq!(9, db.len());
+    assert_eq!(Some(gen_sample(9)), db.get(2));
+    assert!(db.get(9).is_none());
+
+    assert_eq!(Some(gen_sample(8)), db.swap_remove_at(8));
+    assert_eq!(8, db.len());
+    assert!(db.swap_remove_at(8).is_none());
+
+    db.push(gen_sample(100));
+    assert_eq!(Some(gen_sample(100)), db.get(8));
+}
+
+#[test]
+fn t_unordered_retain() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    (0..100).for_each(|i| db.push(gen_sample(i)));
+
+    db.unordered_retain(|v| 0 == v.idx % 2);
+    assert_eq!(50, db.len());
+
+    let mut res = db.iter().map(|v| v.idx).collect::<Vec<_>>();
+    res.sort_unstable();
+    assert_eq!((0..100).step_by(2).collect::<Vec<_>>(), res);
+
+    db.unordered_retain(|_| false);
+    assert!(db.is_empty());
+}
+
+#[test]
+fn t_eq() {
+    crate::clear();
+
+    let mut a = crate::new_vecx!();
+    let mut b = crate::new_vecx!();
+    assert_eq!(a, b);
+
+    (0..100).for_each(|i| {
+        a.push(gen_sample(i));
+        b.push(gen_sample(i));
+    });
+    assert_ne!(a.get_path(), b.get_path());
+    assert_eq!(a, b);
+
+    b.set_value(50, gen_sample(500));
+    assert_ne!(a, b);
+
+    b.set_value(50, gen_sample(50));
+    assert_eq!(a, b);
+
+    // a prefix is not equal to the whole
+    b.push(gen_sample(100));
+    assert_ne!(a, b);
+    assert_ne!(b, a);
+}
+
+#[test]
+fn t_extend() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    (0..10).for_each(|i| db.push(gen_sample(i)));
+
+    let values = (10..110).map(gen_sample).collect::<Vec<_>>();
+    pnk!(db.extend_from_slice(&values));
+    assert_eq!(110, db.len());
+    assert_eq!(Some(values[99].clone()), db.get(109));
+    pnk!(db.extend_from_slice(&[]));
+    assert_eq!(110, db.len());
+
+    let mut other = crate::new_vecx!();
+    pnk!(other.extend_from_vecx(&db));
+    pnk!(other.extend_from_vecx(&db));
+    assert_eq!(220, other.len());
+    (0..220).for_each(|i| assert_eq!(Some(gen_sample(i % 110)), other.get(i)));
+
+    // more than 256 elements, the order of index differs from the storage order
+    let values = (110..300).map(gen_sample).collect::<Vec<_>>();
+    pnk!(db.extend_from_slice(&values));
+    let mut other = crate::new_vecx!();
+    pnk!(other.extend_from_vecx(&db));
+    assert_eq!(300, other.len());
+    (0..300).for_each(|i| assert_eq!(Some(gen_sample(i)), other.get(i)));
+}
+
+#[test]
+fn t_copy_within() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    pnk!(db.extend_from_slice(&["a", "b", "c", "d"].map(|s| s.to_owned())));
+
+    pnk!(db.copy_within(1..3, 0));
+    assert_eq!(vec!["b", "c", "c", "d"], db.iter().collect::<Vec<_>>());
+
+    // overlap in the other direction
+    pnk!(db.copy_within(..2, 1));
+    assert_eq!(vec!["b", "b", "c", "d"], db.iter().collect::<Vec<_>>());
+
+    // extend beyond the end
+    pnk!(db.copy_within(1..=3, 3));
+    assert_eq!(6, db.len());
+    assert_eq!(
+        vec!["b", "b", "c", "b", "c", "d"],
+        db.iter().collect::<Vec<_>>()
+    );
+
+    assert!(db.copy_within(5..7, 0).is_err());
+    assert!(db.copy_within(0..1, 7).is_err());
+    assert_eq!(6, db.len());
+}
+
+#[test]
+fn t_partition_point() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    assert_eq!(0, db.partition_point(|v: &usize| *v < 6));
+
+    db.push(3);
+    assert_eq!(1, db.partition_point(|v| *v < 6));
+    assert_eq!(0, db.partition_point(|v| *v < 3));
+
+    let mut db = crate::new_vecx!();
+    pnk!(db.extend_from_slice(&(1..=10).collect::<Vec<usize>>()));
+    assert_eq!(5, db.partition_point(|v| *v < 6));
+    assert_eq!(0, db.partition_point(|v| *v < 1));
+    assert_eq!(10, db.partition_point(|v| *v <= 10));
+}
+
+#[test]
+fn t_split_at() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    (0..100).for_each(|i| db.push(gen_sample(i)));
+
+    let (first, second) = db.split_at(50);
+    assert_eq!(50, first.len());
+    assert_eq!(50, second.len());
+    assert_eq!(
+        (0..50).map(gen_sample).collect::<Vec<_>>(),
+        first.collect::<Vec<_>>()
+    );
+    assert_eq!(
+        (50..100).rev().map(gen_sample).collect::<Vec<_>>(),
+        second.rev().collect::<Vec<_>>()
+    );
+
+    let (first, second) = db.split_at(0);
+    assert_eq!(0, first.count());
+    assert_eq!(100, second.count());
+
+    let (first, second) = db.split_at(100);
+    assert_eq!(100, first.count());
+    assert_eq!(0, second.count());
+}
+
+#[test]
+fn t_flatten() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    (0..10u32).for_each(|i| db.push((0..5).map(|j| i * 5 + j).collect::<Vec<_>>()));
+
+    let flat = pnk!(db.flatten(&crate::unique_path!()));
+    assert_eq!(50, flat.len());
+    (0..50).for_each(|i| assert_eq!(Some(i as u32), flat.get(i)));
+
+    // more than 256 inner vectors, the order of index differs from the storage order
+    (10..300u32).for_each(|i| db.push((0..5).map(|j| i * 5 + j).collect::<Vec<_>>()));
+    let flat = pnk!(db.flatten(&crate::unique_path!()));
+    assert_eq!(1500, flat.len());
+    (0..1500).for_each(|i| assert_eq!(Some(i as u32), flat.get(i)));
+
+    let empty: Vecx<Vec<u32>> = crate::new_vecx!();
+    assert!(pnk!(empty.flatten(&crate::unique_path!())).is_empty());
+}
+
+#[test]
+fn t_iter_range() {
+    crate::clear();
+
+    let mut db = crate::new_vecx!();
+    (0..50).for_each(|i| db.push(gen_sample(i)));
+
+    let res = db.iter_range(10, 20).collect::<Vec<_>>();
+    assert_eq!((10..20).map(gen_sample).collect::<Vec<_>>(), res);
+    assert_eq!(0, db.iter_range(50, 50).count());
+    assert_eq!(50, db.iter_range(0, 50).count());
+
+    // the indexes are stored in little-endian,
+    // the order of index is kept across the byte boundaries
+    (50..600).for_each(|i| db.push(gen_sample(i)));
+    let res = db.iter_range(250, 520).collect::<Vec<_>>();
+    assert_eq!((250..520).map(gen_sample).collect::<Vec<_>>(), res);
+
+    assert!(std::panic::catch_unwind(|| db.iter_range(20, 10).count()).is_err());
+    assert!(std::panic::catch_unwind(|| db.iter_range(0, 601).count()).is_err());
+}
+
+#[test]
+fn t_try_twice_result() {
+    crate::clear();
+
+    let mut tries = 0;
+    let mut fail = || -> Result<usize> {
+        tries += 1;
+        Err(eg!("always fail"))
+    };
+    assert!(crate::try_twice_result!(fail()).is_err());
+    assert_eq!(2, tries);
+
+    let mut tries = 0;
+    let mut fail_once = || -> Result<usize> {
+        tries += 1;
+        alt!(1 == tries, Err(eg!("fail once")), Ok(tries))
+    };
+    assert_eq!(2, pnk!(crate::try_twice_result!(fail_once())));
+
+    // the callers of `new` can handle the error
+    let path = crate::unique_path!();
+    let db: Vecx<usize> = pnk!(crate::try_twice_result!(Vecx::new(&path)));
+    assert!(db.is_empty());
+    let file = format!("{}/{}/occupied", crate::BNC_DATA_DIR.as_str(), &path);
+    pnk!(fs::write(file, b""));
+    let occupied = format!("{}/occupied/db", &path);
+    assert!(crate::try_twice_result!(Vecx::<usize>::new(&occupied)).is_err());
+}